@@ -1,13 +1,14 @@
-use actix_web::{App, HttpResponse, Responder, web};
+use actix_web::{HttpResponse, Responder, web};
 use anyhow::Result;
+use database_common_lib::actix_extension::health::{Health, MySqlPing, PoolSaturation};
 use database_common_lib::database_connection::set_database_name;
 use database_common_lib::{
     actix_extension::create_http_server,
     database_connection::{DatabaseConnectionData, create_pool},
 };
 use include_dir::include_dir;
-use sqlx::MySqlPool;
-use std::sync::Arc;
+use sqlx::{MySqlPool, Row};
+use std::time::Duration;
 
 // Handler that uses database connection
 async fn get_users(db_pool: web::Data<MySqlPool>) -> impl Responder {
@@ -20,7 +21,12 @@ async fn get_users(db_pool: web::Data<MySqlPool>) -> impl Responder {
             // Convert the users to a format that can be returned as JSON
             let user_list: Vec<_> = users
                 .into_iter()
-                .map(|user| serde_json::json!({ "id": user, "name": user.name }))
+                .map(|user| {
+                    serde_json::json!({
+                        "id": user.try_get::<i64, _>("id").ok(),
+                        "name": user.try_get::<String, _>("name").ok(),
+                    })
+                })
                 .collect();
 
             HttpResponse::Ok().json(user_list)
@@ -34,20 +40,6 @@ async fn get_users(db_pool: web::Data<MySqlPool>) -> impl Responder {
     }
 }
 
-async fn health_check(db_pool: web::Data<MySqlPool>) -> impl Responder {
-    // Test database connection is working
-    match sqlx::query("SELECT 1").execute(db_pool.get_ref()).await {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "status": "healthy",
-            "database": "connected"
-        })),
-        Err(_) => HttpResponse::ServiceUnavailable().json(serde_json::json!({
-            "status": "unhealthy",
-            "database": "disconnected"
-        })),
-    }
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
     // This sets the database name used for the connection.
@@ -63,6 +55,13 @@ async fn main() -> Result<()> {
     // Create a MySQL connection pool
     let pool = create_pool(&db_config).await?;
 
+    // Liveness at /api/health, readiness (database ping + pool saturation) at /api/ready
+    let health = Health::new()
+        .liveness_path("/api/health")
+        .readiness_path("/api/ready")
+        .check(MySqlPing::new(pool.clone()).timeout(Duration::from_secs(2)))
+        .check(PoolSaturation::new(pool.clone()));
+
     // Wrap the pool in web::Data for sharing across handlers
     let db_data = web::Data::new(pool);
 
@@ -70,12 +69,12 @@ async fn main() -> Result<()> {
     let server = create_http_server(
         move || {
             let db_data = db_data.clone();
+            let health = health.clone();
 
             Box::new(move |cfg| {
+                health.configure(cfg);
                 cfg.app_data(db_data.clone()).service(
-                    web::scope("/api")
-                        .route("/users", web::get().to(get_users))
-                        .route("/health", web::get().to(health_check)),
+                    web::scope("/api").route("/users", web::get().to(get_users)),
                 );
             })
         },
@@ -1,19 +1,31 @@
+pub mod cors;
+pub mod health;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod request_id;
+pub mod server;
+pub mod tls;
+
+pub use cors::CorsPolicy;
+pub use health::Health;
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+pub use request_id::{AccessLogFormat, RequestId};
+pub use server::{BuiltinMiddleware, HttpServerBuilder};
+pub use tls::{TlsConfig, TlsSource};
+
 use actix_files::file_extension_to_mime;
 use actix_web::dev::Server;
-use actix_web::dev::Service;
 use actix_web::error::ErrorInternalServerError;
-use actix_web::http::header::{ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_ORIGIN};
 use actix_web::web::Data;
 use actix_web::{
-    App, HttpServer,
+    App,
     dev::{ServiceFactory, ServiceRequest},
     web,
 };
-use actix_web::{Error, HttpRequest, HttpResponse, Responder, get, middleware};
+use actix_web::{Error, HttpRequest, HttpResponse, Responder, get};
 use anyhow::Result;
 use include_dir::Dir;
-use log::error;
-use serde_json::json;
 use vite_actix::vite_app_factory::ViteAppFactory;
 
 /// Serves the index.html file from the embedded static directory.
@@ -89,6 +101,9 @@ where
 
 /// Creates and configures an HTTP server with customized middleware and JSON handling
 ///
+/// Binds `0.0.0.0:{port}` with the [`HttpServerBuilder`] defaults. Use the builder directly to
+/// change the bind address, worker count, body limits, timeouts or built-in middleware.
+///
 /// # Arguments
 /// * `factory` - A function that configures web service routes and settings
 /// * `wwwroot` - Embedded static directory containing the files
@@ -134,41 +149,7 @@ pub fn create_http_server<F>(
 where
     F: Fn() -> Box<dyn FnOnce(&mut web::ServiceConfig) + Send + 'static> + Send + Clone + 'static,
 {
-    let wwwroot = Data::new(wwwroot);
-    let server = HttpServer::new(move || {
-        let config_fn = factory();
-        App::new()
-            .wrap(middleware::Logger::default())
-            .wrap_fn(|req, srv| {
-                // disable cors
-                let fut = srv.call(req);
-                async {
-                    let mut res = fut.await?;
-                    res.headers_mut()
-                        .insert(ACCESS_CONTROL_ALLOW_HEADERS, "*".parse().unwrap());
-                    res.headers_mut()
-                        .insert(ACCESS_CONTROL_ALLOW_ORIGIN, "*".parse().unwrap());
-                    Ok(res)
-                }
-            })
-            .app_data(
-                web::JsonConfig::default()
-                    .limit(4096)
-                    .error_handler(|err, _req| {
-                        error!("Failed to parse JSON: {}", err);
-                        let error = json!({ "error": format!("{}", err) });
-                        actix_web::error::InternalError::from_response(
-                            err,
-                            HttpResponse::BadRequest().json(error),
-                        )
-                        .into()
-                    }),
-            )
-            .configure(|cfg| config_fn(cfg))
-            .configure_routes(wwwroot.clone())
-    })
-    .workers(4)
-    .bind(format!("0.0.0.0:{}", port))?
-    .run();
-    Ok(server)
+    HttpServerBuilder::new(factory, wwwroot)
+        .bind("0.0.0.0", port)
+        .build()
 }
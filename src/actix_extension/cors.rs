@@ -0,0 +1,386 @@
+use actix_cors::Cors;
+use actix_web::http::Method;
+use actix_web::http::Uri;
+use actix_web::http::header::HeaderName;
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+
+// ---------------------------------------------------------------------------
+// Env var names (all optional). See `CorsPolicy::from_env` for semantics.
+// ---------------------------------------------------------------------------
+const ENV_ALLOWED_ORIGINS: &str = "CORS_ALLOWED_ORIGINS";
+const ENV_ALLOWED_METHODS: &str = "CORS_ALLOWED_METHODS";
+const ENV_ALLOWED_HEADERS: &str = "CORS_ALLOWED_HEADERS";
+const ENV_EXPOSED_HEADERS: &str = "CORS_EXPOSED_HEADERS";
+const ENV_ALLOW_CREDENTIALS: &str = "CORS_ALLOW_CREDENTIALS";
+const ENV_MAX_AGE: &str = "CORS_MAX_AGE";
+
+/// Cross-origin resource sharing policy applied by [`HttpServerBuilder`](super::HttpServerBuilder).
+///
+/// Preflight `OPTIONS` requests are answered by the middleware and every response carries
+/// `Vary: Origin`. The default policy mirrors the library's historical behaviour: any origin,
+/// method and header is allowed, and `Access-Control-Allow-Origin: *` is sent.
+///
+/// Origins are either exact (`https://app.example.com`) or a leading-wildcard pattern
+/// (`*.example.com`) that matches any subdomain over any scheme and port.
+#[derive(Clone, Debug)]
+pub struct CorsPolicy {
+    /// Allowed origins. `None` allows any origin.
+    pub allowed_origins: Option<Vec<String>>,
+    /// Allowed request methods. `None` allows any method.
+    pub allowed_methods: Option<Vec<Method>>,
+    /// Allowed request headers. `None` allows any header.
+    pub allowed_headers: Option<Vec<HeaderName>>,
+    /// Response headers exposed to the browser.
+    pub exposed_headers: Vec<HeaderName>,
+    /// Whether `Access-Control-Allow-Credentials: true` is sent.
+    pub allow_credentials: bool,
+    /// How long (in seconds) a preflight response may be cached.
+    pub max_age: Option<usize>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self::permissive()
+    }
+}
+
+impl CorsPolicy {
+    /// Allows any origin, method and header without credentials.
+    pub fn permissive() -> Self {
+        Self {
+            allowed_origins: None,
+            allowed_methods: None,
+            allowed_headers: None,
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: Some(3600),
+        }
+    }
+
+    /// Allows nothing; add origins, methods and headers with the builder methods.
+    pub fn restrictive() -> Self {
+        Self {
+            allowed_origins: Some(Vec::new()),
+            allowed_methods: Some(Vec::new()),
+            allowed_headers: Some(Vec::new()),
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Adds an exact origin or `*.domain` pattern.
+    ///
+    /// # Errors
+    /// Returns an error if `origin` is neither a valid origin URI nor a `*.` pattern.
+    pub fn allow_origin(mut self, origin: &str) -> Result<Self> {
+        validate_origin(origin)?;
+        self.allowed_origins
+            .get_or_insert_with(Vec::new)
+            .push(origin.to_string());
+        Ok(self)
+    }
+
+    /// Adds allowed request methods.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.allowed_methods
+            .get_or_insert_with(Vec::new)
+            .extend(methods);
+        self
+    }
+
+    /// Adds allowed request headers.
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.allowed_headers
+            .get_or_insert_with(Vec::new)
+            .extend(headers);
+        self
+    }
+
+    /// Adds headers exposed to the browser.
+    pub fn expose_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.exposed_headers.extend(headers);
+        self
+    }
+
+    /// Sets whether credentials (cookies, authorization headers) are allowed.
+    ///
+    /// Requires an explicit origin list, see [`validate`](Self::validate).
+    pub fn allow_credentials(mut self, allow: bool) -> Self {
+        self.allow_credentials = allow;
+        self
+    }
+
+    /// Sets the preflight cache lifetime in seconds.
+    pub fn max_age(mut self, seconds: Option<usize>) -> Self {
+        self.max_age = seconds;
+        self
+    }
+
+    /// Loads the policy from environment variables on top of [`CorsPolicy::permissive`].
+    ///
+    /// Only variables that are set modify the policy. List values are comma separated and
+    /// a single `*` means "any":
+    ///
+    /// | Env var                  | Field               |
+    /// |--------------------------|---------------------|
+    /// | `CORS_ALLOWED_ORIGINS`   | `allowed_origins`   |
+    /// | `CORS_ALLOWED_METHODS`   | `allowed_methods`   |
+    /// | `CORS_ALLOWED_HEADERS`   | `allowed_headers`   |
+    /// | `CORS_EXPOSED_HEADERS`   | `exposed_headers`   |
+    /// | `CORS_ALLOW_CREDENTIALS` | `allow_credentials` |
+    /// | `CORS_MAX_AGE`           | `max_age` (seconds) |
+    ///
+    /// # Errors
+    /// * A value cannot be parsed; the error names the variable.
+    /// * `CORS_ALLOW_CREDENTIALS` is enabled without `CORS_ALLOWED_ORIGINS` listing origins.
+    pub fn from_env() -> Result<Self> {
+        let mut policy = Self::permissive();
+
+        if let Ok(v) = std::env::var(ENV_ALLOWED_ORIGINS) {
+            policy.allowed_origins = parse_list(&v, |origin| {
+                validate_origin(origin).map(|_| origin.to_string())
+            })
+            .map_err(|e| anyhow!("{ENV_ALLOWED_ORIGINS}: {e}"))?;
+        }
+        if let Ok(v) = std::env::var(ENV_ALLOWED_METHODS) {
+            policy.allowed_methods = parse_list(&v, |method| {
+                Method::from_str(&method.to_ascii_uppercase())
+                    .map_err(|e| anyhow!("{method:?}: {e}"))
+            })
+            .map_err(|e| anyhow!("{ENV_ALLOWED_METHODS}: {e}"))?;
+        }
+        if let Ok(v) = std::env::var(ENV_ALLOWED_HEADERS) {
+            policy.allowed_headers =
+                parse_list(&v, parse_header).map_err(|e| anyhow!("{ENV_ALLOWED_HEADERS}: {e}"))?;
+        }
+        if let Ok(v) = std::env::var(ENV_EXPOSED_HEADERS) {
+            policy.exposed_headers = parse_list(&v, parse_header)
+                .map_err(|e| anyhow!("{ENV_EXPOSED_HEADERS}: {e}"))?
+                .unwrap_or_default();
+        }
+        if let Ok(v) = std::env::var(ENV_ALLOW_CREDENTIALS) {
+            policy.allow_credentials = parse_bool(&v)
+                .ok_or_else(|| anyhow!("{ENV_ALLOW_CREDENTIALS} must be a boolean (got {v:?})"))?;
+        }
+        if let Ok(v) = std::env::var(ENV_MAX_AGE) {
+            let max_age: usize = v.parse().map_err(|e| {
+                anyhow!("{ENV_MAX_AGE} must be a number of seconds (got {v:?}): {e}")
+            })?;
+            policy.max_age = Some(max_age);
+        }
+
+        policy.validate().map_err(|e| {
+            anyhow!("{ENV_ALLOW_CREDENTIALS} requires {ENV_ALLOWED_ORIGINS} to list origins: {e}")
+        })?;
+        Ok(policy)
+    }
+
+    /// Checks that the policy is safe to apply.
+    ///
+    /// # Errors
+    /// Returns an error if credentials are allowed for any origin, which would let every site
+    /// make credentialed requests on behalf of the user.
+    pub fn validate(&self) -> Result<()> {
+        if self.allow_credentials && self.allowed_origins.is_none() {
+            return Err(anyhow!(
+                "credentials cannot be allowed for any origin; list the allowed origins"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds the `actix-cors` middleware for this policy.
+    ///
+    /// # Errors
+    /// Returns an error if the policy is invalid, see [`validate`](Self::validate).
+    pub fn to_cors(&self) -> Result<Cors> {
+        self.validate()?;
+        Ok(self.build_cors())
+    }
+
+    /// Builds the middleware of a policy that passed [`validate`](Self::validate).
+    pub(crate) fn build_cors(&self) -> Cors {
+        let mut cors = Cors::default();
+
+        cors = match &self.allowed_origins {
+            // Always a literal `*`: the request origin is never echoed back, so browsers refuse
+            // credentialed requests even if `allow_credentials` slipped past validation.
+            None => cors.allow_any_origin().send_wildcard(),
+            Some(origins) => {
+                let (patterns, exact): (Vec<_>, Vec<_>) =
+                    origins.iter().partition(|origin| origin.starts_with("*."));
+                for origin in exact {
+                    cors = cors.allowed_origin(origin);
+                }
+                if !patterns.is_empty() {
+                    let suffixes: Vec<String> = patterns
+                        .into_iter()
+                        .map(|pattern| pattern[1..].to_ascii_lowercase())
+                        .collect();
+                    cors = cors.allowed_origin_fn(move |origin, _req| {
+                        origin_host(origin.to_str().unwrap_or_default())
+                            .map(|host| suffixes.iter().any(|suffix| host.ends_with(suffix)))
+                            .unwrap_or(false)
+                    });
+                }
+                cors
+            }
+        };
+
+        cors = match &self.allowed_methods {
+            None => cors.allow_any_method(),
+            Some(methods) => cors.allowed_methods(methods.clone()),
+        };
+
+        cors = match &self.allowed_headers {
+            None => cors.allow_any_header(),
+            Some(headers) => cors.allowed_headers(headers.clone()),
+        };
+
+        if !self.exposed_headers.is_empty() {
+            cors = cors.expose_headers(self.exposed_headers.clone());
+        }
+        if self.allow_credentials && self.allowed_origins.is_some() {
+            cors = cors.supports_credentials();
+        }
+
+        cors.max_age(self.max_age)
+    }
+}
+
+/// Parses a comma separated list, returning `None` for the `*` wildcard.
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Result<T>) -> Result<Option<Vec<T>>> {
+    let items: Vec<&str> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect();
+    if items == ["*"] {
+        return Ok(None);
+    }
+    items
+        .into_iter()
+        .map(parse)
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+fn parse_header(header: &str) -> Result<HeaderName> {
+    HeaderName::from_str(header).map_err(|e| anyhow!("{header:?}: {e}"))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Accepts `*.domain` patterns and absolute `scheme://host[:port]` origins.
+fn validate_origin(origin: &str) -> Result<()> {
+    if let Some(domain) = origin.strip_prefix("*.") {
+        if domain.is_empty() || domain.contains(['/', ':', '*']) {
+            return Err(anyhow!("invalid origin pattern {origin:?}"));
+        }
+        return Ok(());
+    }
+    let uri = Uri::from_str(origin).map_err(|e| anyhow!("invalid origin {origin:?}: {e}"))?;
+    if uri.scheme().is_none() || uri.host().is_none() {
+        return Err(anyhow!(
+            "invalid origin {origin:?}: expected scheme://host[:port]"
+        ));
+    }
+    Ok(())
+}
+
+/// Extracts the lower-cased host from an `Origin` header value.
+fn origin_host(origin: &str) -> Option<String> {
+    Uri::from_str(origin)
+        .ok()?
+        .host()
+        .map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header;
+    use actix_web::test::{TestRequest, call_service, init_service};
+    use actix_web::{App, HttpResponse, web};
+
+    #[test]
+    fn credentials_require_an_origin_list() {
+        let policy = CorsPolicy::permissive().allow_credentials(true);
+        assert!(policy.validate().is_err());
+        assert!(policy.to_cors().is_err());
+
+        let policy = CorsPolicy::permissive()
+            .allow_origin("https://app.example.com")
+            .unwrap()
+            .allow_credentials(true);
+        assert!(policy.to_cors().is_ok());
+    }
+
+    #[actix_web::test]
+    async fn credentials_are_only_granted_to_listed_origins() {
+        let policy = CorsPolicy::restrictive()
+            .allow_origin("https://app.example.com")
+            .unwrap()
+            .allow_methods([Method::GET])
+            .allow_credentials(true);
+        let app = init_service(
+            App::new()
+                .wrap(policy.to_cors().unwrap())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for (origin, allowed) in [
+            ("https://app.example.com", true),
+            ("https://evil.example.net", false),
+        ] {
+            let req = TestRequest::get()
+                .uri("/")
+                .insert_header((header::ORIGIN, origin))
+                .to_request();
+            let res = call_service(&app, req).await;
+            let allow_origin = res
+                .headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .map(|v| v.to_str().unwrap().to_string());
+            assert_eq!(allow_origin.as_deref() == Some(origin), allowed, "{origin}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn any_origin_is_never_reflected() {
+        let mut policy = CorsPolicy::permissive();
+        // Bypasses `allow_credentials` and `validate` to check the middleware itself
+        policy.allow_credentials = true;
+        let app = init_service(
+            App::new()
+                .wrap(policy.build_cors())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header((header::ORIGIN, "https://evil.example.net"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        let headers = res.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "*"
+        );
+        assert!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .is_none()
+        );
+    }
+}
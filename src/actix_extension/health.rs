@@ -0,0 +1,444 @@
+use crate::database_connection::ReloadablePool;
+use actix_web::{HttpResponse, web};
+use anyhow::{Result, anyhow};
+use futures_util::future::join_all;
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::MySqlPool;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Default path of the liveness endpoint.
+pub const DEFAULT_LIVENESS_PATH: &str = "/health";
+/// Default path of the readiness endpoint.
+pub const DEFAULT_READINESS_PATH: &str = "/ready";
+/// Default upper bound on how long a single check may run before it is reported as down.
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Future returned by [`HealthCheck::check`].
+///
+/// Resolves to optional details included in the report, or an error marking the check as down.
+pub type CheckFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<Value>>> + Send + 'a>>;
+
+/// A single readiness check.
+pub trait HealthCheck: Send + Sync + 'static {
+    /// Name shown in the report.
+    fn name(&self) -> &str;
+
+    /// Runs the check.
+    fn check(&self) -> CheckFuture<'_>;
+}
+
+/// Error for a failed check that still reports its details.
+///
+/// Return it from [`HealthCheck::check`] (converted into an [`anyhow::Error`]) to keep the
+/// details next to the error in the report.
+#[derive(Debug)]
+pub struct CheckFailure {
+    message: String,
+    details: Value,
+}
+
+impl CheckFailure {
+    pub fn new(message: impl Into<String>, details: Value) -> Self {
+        Self {
+            message: message.into(),
+            details,
+        }
+    }
+}
+
+impl std::fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CheckFailure {}
+
+/// Overall or per-check status.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// Result of a single check.
+#[derive(Serialize, Clone, Debug)]
+pub struct CheckReport {
+    pub name: String,
+    pub status: HealthStatus,
+    /// Time the check took, in milliseconds.
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of all readiness checks.
+#[derive(Serialize, Clone, Debug)]
+pub struct HealthReport {
+    /// `Up` only when every check is up.
+    pub status: HealthStatus,
+    pub checks: Vec<CheckReport>,
+}
+
+impl HealthReport {
+    /// Renders the report as JSON with `200 OK` when up and `503 Service Unavailable` when down.
+    pub fn to_response(&self) -> HttpResponse {
+        match self.status {
+            HealthStatus::Up => HttpResponse::Ok().json(self),
+            HealthStatus::Down => HttpResponse::ServiceUnavailable().json(self),
+        }
+    }
+}
+
+/// Mountable liveness and readiness endpoints.
+///
+/// * **Liveness** (`/health` by default) answers `200` as long as the process can serve
+///   requests; it runs no checks.
+/// * **Readiness** (`/ready` by default) runs every registered check concurrently and answers
+///   `200` when all are up or `503` otherwise, with a [`HealthReport`] body.
+///
+/// ```norust
+/// use database_common_lib::actix_extension::health::{Health, MySqlPing, PoolSaturation};
+///
+/// let health = Health::new()
+///     .check(MySqlPing::new(pool.clone()))
+///     .check(PoolSaturation::new(pool.clone()))
+///     .check_fn("cache", || async { Ok(None) });
+///
+/// HttpServerBuilder::new(factory, wwwroot).health(health);
+/// // or, inside a factory: cfg.configure(|cfg| health.configure(cfg));
+/// ```
+#[derive(Clone)]
+pub struct Health {
+    liveness_path: String,
+    readiness_path: String,
+    timeout: Duration,
+    checks: Vec<Arc<dyn HealthCheck>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Health {
+    /// Creates the endpoints at their default paths with no checks.
+    pub fn new() -> Self {
+        Self {
+            liveness_path: DEFAULT_LIVENESS_PATH.to_string(),
+            readiness_path: DEFAULT_READINESS_PATH.to_string(),
+            timeout: DEFAULT_CHECK_TIMEOUT,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Sets the path of the liveness endpoint.
+    pub fn liveness_path(mut self, path: impl Into<String>) -> Self {
+        self.liveness_path = path.into();
+        self
+    }
+
+    /// Sets the path of the readiness endpoint.
+    pub fn readiness_path(mut self, path: impl Into<String>) -> Self {
+        self.readiness_path = path.into();
+        self
+    }
+
+    /// Returns the liveness and readiness paths.
+    pub(crate) fn endpoints(&self) -> [&str; 2] {
+        [&self.liveness_path, &self.readiness_path]
+    }
+
+    /// Sets how long each check may run before it is reported as down.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a readiness check.
+    pub fn check(mut self, check: impl HealthCheck) -> Self {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    /// Adds a readiness check backed by an async closure.
+    pub fn check_fn<F, Fut>(self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Option<Value>>> + Send + 'static,
+    {
+        self.check(FnCheck {
+            name: name.into(),
+            check,
+        })
+    }
+
+    /// Runs every check concurrently and collects the results.
+    pub async fn report(&self) -> HealthReport {
+        let checks = join_all(
+            self.checks
+                .iter()
+                .map(|check| run_check(check.as_ref(), self.timeout)),
+        )
+        .await;
+        let status = if checks.iter().all(|c| c.status == HealthStatus::Up) {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+        HealthReport { status, checks }
+    }
+
+    /// Registers the liveness and readiness routes.
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        let health = self.clone();
+        cfg.route(
+            &self.liveness_path,
+            web::get().to(|| async { HttpResponse::Ok().json(json!({ "status": "up" })) }),
+        )
+        .route(
+            &self.readiness_path,
+            web::get().to(move || {
+                let health = health.clone();
+                async move { health.report().await.to_response() }
+            }),
+        );
+    }
+}
+
+/// Runs `check` with a timeout, timing it.
+async fn run_check(check: &dyn HealthCheck, timeout: Duration) -> CheckReport {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check.check()).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("timed out after {}ms", timeout.as_millis())),
+    };
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    match result {
+        Ok(details) => CheckReport {
+            name: check.name().to_string(),
+            status: HealthStatus::Up,
+            latency_ms,
+            details,
+            error: None,
+        },
+        Err(e) => CheckReport {
+            name: check.name().to_string(),
+            status: HealthStatus::Down,
+            latency_ms,
+            details: e
+                .downcast_ref::<CheckFailure>()
+                .map(|failure| failure.details.clone()),
+            error: Some(format!("{e:#}")),
+        },
+    }
+}
+
+/// Pool checked by [`MySqlPing`] and [`PoolSaturation`].
+enum CheckedPool {
+    Fixed(MySqlPool),
+    /// Resolved on every check so pool swaps are followed.
+    Reloadable(ReloadablePool),
+}
+
+impl CheckedPool {
+    fn current(&self) -> MySqlPool {
+        match self {
+            CheckedPool::Fixed(pool) => pool.clone(),
+            CheckedPool::Reloadable(pool) => pool.pool(),
+        }
+    }
+}
+
+/// Runs `SELECT 1` against a MySQL pool.
+pub struct MySqlPing {
+    pool: CheckedPool,
+    timeout: Option<Duration>,
+}
+
+impl MySqlPing {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self {
+            pool: CheckedPool::Fixed(pool),
+            timeout: None,
+        }
+    }
+
+    /// Checks the current pool of a [`ReloadablePool`].
+    pub fn reloadable(pool: ReloadablePool) -> Self {
+        Self {
+            pool: CheckedPool::Reloadable(pool),
+            timeout: None,
+        }
+    }
+
+    /// Sets a timeout shorter than the [`Health`] default for this check.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl HealthCheck for MySqlPing {
+    fn name(&self) -> &str {
+        "mysql"
+    }
+
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let pool = self.pool.current();
+            let ping = sqlx::query("SELECT 1").execute(&pool);
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, ping)
+                    .await
+                    .map_err(|_| anyhow!("ping timed out after {}ms", timeout.as_millis()))??,
+                None => ping.await?,
+            };
+            Ok(None)
+        })
+    }
+}
+
+/// Reports down when the share of in-use connections reaches a threshold.
+pub struct PoolSaturation {
+    pool: CheckedPool,
+    max_ratio: f64,
+}
+
+impl PoolSaturation {
+    /// Creates the check with a threshold of 90% of `max_connections`.
+    pub fn new(pool: MySqlPool) -> Self {
+        Self {
+            pool: CheckedPool::Fixed(pool),
+            max_ratio: 0.9,
+        }
+    }
+
+    /// Checks the current pool of a [`ReloadablePool`], with a threshold of 90% of
+    /// `max_connections`.
+    pub fn reloadable(pool: ReloadablePool) -> Self {
+        Self {
+            pool: CheckedPool::Reloadable(pool),
+            max_ratio: 0.9,
+        }
+    }
+
+    /// Sets the in-use/max ratio (0.0–1.0) at which the pool is reported as saturated.
+    pub fn max_ratio(mut self, ratio: f64) -> Self {
+        self.max_ratio = ratio;
+        self
+    }
+}
+
+impl HealthCheck for PoolSaturation {
+    fn name(&self) -> &str {
+        "mysql_pool"
+    }
+
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            let pool = self.pool.current();
+            saturation(
+                pool.size(),
+                pool.num_idle() as u32,
+                pool.options().get_max_connections(),
+                self.max_ratio,
+            )
+        })
+    }
+}
+
+/// Reports the pool counts, failing with the same details once `max_ratio` is reached.
+fn saturation(size: u32, idle: u32, max: u32, max_ratio: f64) -> Result<Option<Value>> {
+    let in_use = size.saturating_sub(idle);
+    let details = json!({ "size": size, "idle": idle, "in_use": in_use, "max": max });
+
+    if max > 0 && f64::from(in_use) / f64::from(max) >= max_ratio {
+        let message = format!(
+            "{in_use} of {max} connections in use (threshold {:.0}%)",
+            max_ratio * 100.0
+        );
+        return Err(CheckFailure::new(message, details).into());
+    }
+    Ok(Some(details))
+}
+
+/// Checks that the remote configuration endpoint is reachable.
+///
+/// See [`ping_remote_config`](crate::database_connection::ping_remote_config).
+#[derive(Default)]
+pub struct RemoteConfigReachable;
+
+impl HealthCheck for RemoteConfigReachable {
+    fn name(&self) -> &str {
+        "remote_config"
+    }
+
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            crate::database_connection::ping_remote_config().await?;
+            Ok(None)
+        })
+    }
+}
+
+/// Check backed by an async closure, created by [`Health::check_fn`].
+struct FnCheck<F> {
+    name: String,
+    check: F,
+}
+
+impl<F, Fut> HealthCheck for FnCheck<F>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<Value>>> + Send + 'static,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin((self.check)())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn saturated_pools_report_their_details() {
+        let report = Health::new()
+            .check_fn("mysql_pool", || async { saturation(10, 1, 10, 0.9) })
+            .check_fn("reporting_pool", || async { saturation(10, 8, 10, 0.9) })
+            .report()
+            .await;
+
+        assert_eq!(report.status, HealthStatus::Down);
+        let saturated = &report.checks[0];
+        assert_eq!(saturated.status, HealthStatus::Down);
+        assert_eq!(
+            saturated.error.as_deref(),
+            Some("9 of 10 connections in use (threshold 90%)")
+        );
+        assert_eq!(
+            saturated.details,
+            Some(json!({ "size": 10, "idle": 1, "in_use": 9, "max": 10 }))
+        );
+        let healthy = &report.checks[1];
+        assert_eq!(healthy.status, HealthStatus::Up);
+        assert_eq!(
+            healthy.details,
+            Some(json!({ "size": 10, "idle": 8, "in_use": 2, "max": 10 }))
+        );
+    }
+}
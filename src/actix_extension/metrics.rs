@@ -0,0 +1,394 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::{Condition, Next, from_fn};
+use actix_web::{App, Error, HttpResponse, web};
+use anyhow::Result;
+use log::error;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use sqlx::MySqlPool;
+use sqlx::mysql::MySqlConnectOptions;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Default path the Prometheus text exposition is served on.
+pub const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+/// Prometheus metrics for HTTP requests and MySQL connection pools.
+///
+/// Requires the `metrics` cargo feature. Install with
+/// [`HttpServerBuilder::metrics`](super::HttpServerBuilder::metrics).
+///
+/// | Metric                          | Type      | Labels                           |
+/// |---------------------------------|-----------|----------------------------------|
+/// | `http_requests_total`           | counter   | `method`, `route`, `status`      |
+/// | `http_request_duration_seconds` | histogram | `method`, `route`, `status`      |
+/// | `db_pool_connections`           | gauge     | `pool`, `state` (`idle`/`in_use`)|
+/// | `db_pool_size`                  | gauge     | `pool`                           |
+/// | `db_pool_max_connections`       | gauge     | `pool`                           |
+///
+/// `route` is the matched route pattern (e.g. `/api/echo/{message}`), or `unmatched`, so path
+/// parameters do not create new series. `status` is the status class (`2xx`, `4xx`, ...), and
+/// `method` is `OTHER` for anything but the standard methods, so clients cannot create series
+/// with made-up methods.
+///
+/// Pools created by [`create_pool`](crate::database_connection::create_pool) are reported
+/// automatically, labelled `host:port/database`; other pools can be added with
+/// [`Metrics::register_pool`]. A pool is counted once even if it is also registered, and pools
+/// sharing a label, e.g. while a [`ReloadablePool`](crate::database_connection::ReloadablePool)
+/// drains its previous pool, are summed.
+#[derive(Clone)]
+pub struct Metrics {
+    path: String,
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pool_size: IntGaugeVec,
+    pool_max: IntGaugeVec,
+    pools: Arc<Mutex<Vec<(String, MySqlPool)>>>,
+}
+
+impl Metrics {
+    /// Creates the metrics in a fresh registry, served on [`DEFAULT_METRICS_PATH`].
+    ///
+    /// # Errors
+    /// Returns an error if the metrics cannot be registered.
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total number of HTTP requests"),
+            &["method", "route", "status"],
+        )?;
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency in seconds",
+            ),
+            &["method", "route", "status"],
+        )?;
+        let pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "MySQL pool connections by state"),
+            &["pool", "state"],
+        )?;
+        let pool_size = IntGaugeVec::new(
+            Opts::new("db_pool_size", "Open MySQL pool connections"),
+            &["pool"],
+        )?;
+        let pool_max = IntGaugeVec::new(
+            Opts::new("db_pool_max_connections", "Maximum MySQL pool connections"),
+            &["pool"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(latency.clone()))?;
+        registry.register(Box::new(pool_connections.clone()))?;
+        registry.register(Box::new(pool_size.clone()))?;
+        registry.register(Box::new(pool_max.clone()))?;
+
+        Ok(Self {
+            path: DEFAULT_METRICS_PATH.to_string(),
+            registry,
+            requests,
+            latency,
+            pool_connections,
+            pool_size,
+            pool_max,
+            pools: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Sets the path the metrics are served on.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Returns the path the metrics are served on.
+    pub(crate) fn endpoint(&self) -> &str {
+        &self.path
+    }
+
+    /// Adds a pool not created by this library to the pool gauges.
+    pub fn register_pool(&self, name: impl Into<String>, pool: MySqlPool) {
+        self.pools
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((name.into(), pool));
+    }
+
+    /// Returns the underlying registry so applications can register their own metrics.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Refreshes the pool gauges and renders every metric in Prometheus text format.
+    ///
+    /// # Errors
+    /// Returns an error if encoding fails.
+    pub fn render(&self) -> Result<String> {
+        self.update_pool_gauges();
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    fn observe(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let status = format!("{}xx", status / 100);
+        let labels = [method, route, status.as_str()];
+        self.requests.with_label_values(&labels).inc();
+        self.latency.with_label_values(&labels).observe(seconds);
+    }
+
+    fn update_pool_gauges(&self) {
+        let registered = self.pools.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let stats = pool_stats(&registered, crate::database_connection::tracked_pools());
+
+        self.pool_connections.reset();
+        self.pool_size.reset();
+        self.pool_max.reset();
+        for (name, stats) in &stats {
+            self.pool_connections
+                .with_label_values(&[name.as_str(), "idle"])
+                .set(stats.idle);
+            self.pool_connections
+                .with_label_values(&[name.as_str(), "in_use"])
+                .set((stats.size - stats.idle).max(0));
+            self.pool_size
+                .with_label_values(&[name.as_str()])
+                .set(stats.size);
+            self.pool_max
+                .with_label_values(&[name.as_str()])
+                .set(stats.max);
+        }
+    }
+}
+
+/// Connection counts of the pools sharing one `pool` label.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct PoolStats {
+    idle: i64,
+    size: i64,
+    max: i64,
+}
+
+/// Label of a pool created by this library, so the same database on different servers gets
+/// separate series.
+fn pool_label(options: &MySqlConnectOptions) -> String {
+    format!(
+        "{}:{}/{}",
+        options.get_host(),
+        options.get_port(),
+        options.get_database().unwrap_or("default")
+    )
+}
+
+/// Sums the open pools by label. `registered` pools keep their name and are not counted again
+/// when also `tracked`.
+fn pool_stats(
+    registered: &[(String, MySqlPool)],
+    tracked: Vec<MySqlPool>,
+) -> BTreeMap<String, PoolStats> {
+    let tracked = tracked
+        .into_iter()
+        .map(|pool| (pool_label(&pool.connect_options()), pool));
+    let pools = registered
+        .iter()
+        .cloned()
+        .chain(tracked)
+        .filter(|(_, pool)| !pool.is_closed())
+        .map(|(name, pool)| {
+            let stats = PoolStats {
+                idle: pool.num_idle() as i64,
+                size: i64::from(pool.size()),
+                max: i64::from(pool.options().get_max_connections()),
+            };
+            (name, pool.connect_options(), stats)
+        });
+    sum_by_label(pools)
+}
+
+/// Adds up `pools` by name, skipping repeated pools. Clones of a pool share their connect
+/// options, which identify the pool.
+fn sum_by_label(
+    pools: impl IntoIterator<Item = (String, Arc<MySqlConnectOptions>, PoolStats)>,
+) -> BTreeMap<String, PoolStats> {
+    let mut seen: Vec<Arc<MySqlConnectOptions>> = Vec::new();
+    let mut stats = BTreeMap::<String, PoolStats>::new();
+    for (name, identity, pool) in pools {
+        if seen.iter().any(|seen| Arc::ptr_eq(seen, &identity)) {
+            continue;
+        }
+        seen.push(identity);
+        let entry = stats.entry(name).or_default();
+        entry.idle += pool.idle;
+        entry.size += pool.size;
+        entry.max += pool.max;
+    }
+    stats
+}
+
+/// Wraps `app` with the request metrics middleware and mounts the exposition route.
+///
+/// Must be applied before the catch-all asset routes so the metrics path is not shadowed.
+pub(crate) fn install<T, B>(
+    app: App<T>,
+    metrics: Option<Metrics>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+>
+where
+    T: ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<B>,
+            Error = Error,
+            InitError = (),
+        > + 'static,
+    B: MessageBody + 'static,
+{
+    let enabled = metrics.is_some();
+    app.configure(|cfg| {
+        if let Some(metrics) = &metrics {
+            let handle = metrics.clone();
+            cfg.route(
+                &metrics.path,
+                web::get().to(move || {
+                    let metrics = handle.clone();
+                    async move {
+                        match metrics.render() {
+                            Ok(body) => HttpResponse::Ok()
+                                .content_type(TextEncoder::new().format_type())
+                                .body(body),
+                            Err(e) => {
+                                error!("Failed to render metrics: {e:#}");
+                                HttpResponse::InternalServerError().finish()
+                            }
+                        }
+                    }
+                }),
+            );
+        }
+    })
+    .app_data(metrics)
+    .wrap(Condition::new(enabled, from_fn(record_request)))
+}
+
+/// Records the count and latency of every request.
+async fn record_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let metrics = req.app_data::<Option<Metrics>>().cloned().flatten();
+    let method = method_label(req.method());
+    let started = Instant::now();
+
+    let res = next.call(req).await?;
+
+    if let Some(metrics) = metrics {
+        let route = res
+            .request()
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        metrics.observe(
+            method,
+            &route,
+            res.status().as_u16(),
+            started.elapsed().as_secs_f64(),
+        );
+    }
+    Ok(res)
+}
+
+/// Label of `method`: its name for the standard methods, `OTHER` for extension methods.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::CONNECT => "CONNECT",
+        Method::OPTIONS => "OPTIONS",
+        Method::TRACE => "TRACE",
+        Method::PATCH => "PATCH",
+        _ => "OTHER",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{TestRequest, call_service, init_service};
+
+    #[test]
+    fn pools_are_labelled_by_host_and_counted_once() {
+        let options = |host: &str| Arc::new(MySqlConnectOptions::new().host(host).database("shop"));
+        let stats = |max| PoolStats {
+            idle: 1,
+            size: 2,
+            max,
+        };
+        let primary = options("db1");
+        let replica = options("db2");
+        let rotated = options("db1");
+        assert_eq!(pool_label(&primary), "db1:3306/shop");
+        assert_eq!(pool_label(&primary), pool_label(&rotated));
+
+        let summed = sum_by_label([
+            ("reporting".to_string(), replica.clone(), stats(7)),
+            (pool_label(&primary), primary.clone(), stats(5)),
+            (pool_label(&replica), replica, stats(7)),
+            (pool_label(&rotated), rotated, stats(3)),
+            (pool_label(&primary), primary, stats(5)),
+        ]);
+        assert_eq!(
+            summed.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "db1:3306/shop".to_string(),
+                    PoolStats {
+                        idle: 2,
+                        size: 4,
+                        max: 8
+                    }
+                ),
+                ("reporting".to_string(), stats(7)),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn extension_methods_share_one_series() {
+        let metrics = Metrics::new().unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(Some(metrics.clone()))
+                .wrap(from_fn(record_request))
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+
+        for method in ["FOO1", "FOO2", "GET"] {
+            let req = TestRequest::default()
+                .method(Method::from_bytes(method.as_bytes()).unwrap())
+                .uri("/anything")
+                .to_request();
+            call_service(&app, req).await;
+        }
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains(r#"method="OTHER""#));
+        assert!(rendered.contains(r#"method="GET""#));
+        assert!(!rendered.contains("FOO"));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::http_error::context::RequestContext;
+use actix_web::body::{BodySize, EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{ACCEPT, ACCEPT_LANGUAGE, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use log::info;
+use serde_json::json;
+use std::fmt;
+use std::future::{Ready, ready};
+use std::time::Instant;
+
+/// Header used to receive and echo request IDs.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Log target of the structured access log lines.
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// Longest incoming request ID that is honoured; longer values are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Format of the per-request access log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// Unstructured text lines from `middleware::Logger::default()`.
+    #[default]
+    Text,
+    /// One JSON object per request, logged at `info` level on the [`ACCESS_LOG_TARGET`] target.
+    Json,
+}
+
+/// ID of the current request.
+///
+/// Taken from the incoming `X-Request-Id` header when it is present and well-formed, otherwise
+/// generated. Use it as an extractor to include the ID in application logs:
+///
+/// ```norust
+/// async fn handler(request_id: RequestId) -> impl Responder {
+///     log::info!("[{request_id}] handling request");
+///     ...
+/// }
+/// ```
+///
+/// Extraction fails with `500` if request IDs are disabled on the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generates a new random (UUID v4) ID.
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Returns the ID as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Uses the incoming header value if it is short and printable, otherwise generates an ID.
+    fn from_header(value: Option<&HeaderValue>) -> Self {
+        value
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LEN
+                    && id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+            })
+            .map(|id| Self(id.to_string()))
+            .unwrap_or_else(Self::generate)
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<RequestId>().cloned().ok_or_else(|| {
+            actix_web::error::ErrorInternalServerError("request IDs are not enabled")
+        }))
+    }
+}
+
+/// Settings read by [`request_context`] from app data.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RequestContextSettings {
+    pub request_id: bool,
+    pub json_access_log: bool,
+}
+
+/// Assigns the request ID, makes the [`RequestContext`] available to error rendering, and
+/// writes the JSON access log line.
+pub(crate) async fn request_context(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let settings =
+        req.app_data::<RequestContextSettings>()
+            .copied()
+            .unwrap_or(RequestContextSettings {
+                request_id: false,
+                json_access_log: false,
+            });
+    let started = Instant::now();
+
+    let request_id = settings
+        .request_id
+        .then(|| RequestId::from_header(req.headers().get(&REQUEST_ID_HEADER)));
+    if let Some(request_id) = &request_id {
+        req.extensions_mut().insert(request_id.clone());
+    }
+
+    let context = RequestContext {
+        request_id: request_id.as_ref().map(|id| id.to_string()),
+        method: req.method().to_string(),
+        path: req.path().to_string(),
+        accept: req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        accept_language: req
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    };
+    // Errors of inner middleware are rendered here so they get the header and log line as well
+    let http_req = req.request().clone();
+    let mut res = match context.clone().scope(next.call(req)).await {
+        Ok(res) => res.map_into_left_body(),
+        Err(e) => ServiceResponse::from_err(e, http_req).map_into_right_body(),
+    };
+
+    if let Some(request_id) = &request_id
+        && let Ok(value) = HeaderValue::from_str(request_id.as_str())
+    {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if settings.json_access_log {
+        let request = res.request();
+        let bytes = match res.response().body().size() {
+            BodySize::Sized(size) => Some(size),
+            BodySize::None => Some(0),
+            BodySize::Stream => None,
+        };
+        let line = json!({
+            "method": context.method,
+            "path": context.path,
+            "route": request.match_pattern(),
+            "status": res.status().as_u16(),
+            "latency_ms": started.elapsed().as_secs_f64() * 1000.0,
+            "bytes": bytes,
+            "client_ip": request.connection_info().realip_remote_addr(),
+            "request_id": context.request_id,
+        });
+        info!(target: ACCESS_LOG_TARGET, "{line}");
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{TestRequest, call_service, init_service};
+    use actix_web::{App, HttpResponse, web};
+
+    async fn reject(
+        _req: ServiceRequest,
+        _next: Next<impl MessageBody>,
+    ) -> Result<ServiceResponse<impl MessageBody>, Error> {
+        Err::<ServiceResponse, _>(actix_web::error::ErrorForbidden("rejected"))
+    }
+
+    #[actix_web::test]
+    async fn middleware_errors_keep_the_request_id() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(reject))
+                .wrap(from_fn(request_context))
+                .app_data(RequestContextSettings {
+                    request_id: true,
+                    json_access_log: true,
+                })
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let request = TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "abc-123"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "abc-123"
+        );
+    }
+}
@@ -0,0 +1,680 @@
+use crate::actix_extension::AssetsAppConfig;
+use crate::actix_extension::cors::CorsPolicy;
+use crate::actix_extension::health::Health;
+#[cfg(feature = "metrics")]
+use crate::actix_extension::metrics::Metrics;
+use crate::actix_extension::request_id::{
+    AccessLogFormat, RequestContextSettings, request_context,
+};
+use crate::actix_extension::tls::TlsConfig;
+use crate::http_error::extractor::ExtractorError;
+use crate::http_error::i18n::load_catalogs;
+use crate::http_error::problem::{ErrorFormat, set_error_format};
+use crate::http_error::redaction::set_verbose_errors;
+use crate::http_error::reporting::{ErrorReporter, register_error_reporter};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Server, ServiceRequest, ServiceResponse};
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError, UrlencodedError};
+use actix_web::http::header::{CONTENT_TYPE, LOCATION};
+use actix_web::http::{KeepAlive, StatusCode};
+use actix_web::middleware::{Condition, ErrorHandlerResponse, ErrorHandlers, Next, from_fn};
+use actix_web::web::Data;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, ResponseError, middleware, web};
+use include_dir::Dir;
+use log::{debug, error, info};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Number of worker threads used when none is configured.
+pub const DEFAULT_WORKERS: usize = 4;
+/// Maximum JSON body size (in bytes) accepted by `web::Json` when none is configured.
+pub const DEFAULT_JSON_LIMIT: usize = 4096;
+/// Maximum raw payload size (in bytes) accepted by `web::Bytes`/`String` when none is configured.
+pub const DEFAULT_PAYLOAD_LIMIT: usize = 262_144;
+/// Seconds given to workers to finish in-flight requests on shutdown.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+
+/// Async callback run by [`HttpServerBuilder::run`] before the server starts or after it stops.
+type LifecycleHook = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = anyhow::Result<()>>>>>;
+
+/// Selects which of the library's built-in middleware are installed on every app.
+#[derive(Clone, Copy, Debug)]
+pub struct BuiltinMiddleware {
+    /// Logs every request in the [`access_log`](BuiltinMiddleware::access_log) format.
+    pub logger: bool,
+    /// Format of the access log written when [`logger`](BuiltinMiddleware::logger) is enabled.
+    pub access_log: AccessLogFormat,
+    /// Honours or generates an `X-Request-Id` for every request and echoes it in the response
+    /// and in error bodies.
+    pub request_id: bool,
+    /// Applies the configured [`CorsPolicy`] (wildcard by default).
+    pub cors: bool,
+}
+
+impl Default for BuiltinMiddleware {
+    fn default() -> Self {
+        Self {
+            logger: true,
+            access_log: AccessLogFormat::Text,
+            request_id: true,
+            cors: true,
+        }
+    }
+}
+
+/// Builder for an Actix-web [`Server`] with the library's routes, error handling and middleware.
+///
+/// [`create_http_server`](crate::actix_extension::create_http_server) is a thin wrapper over
+/// this builder using the defaults below; use the builder directly when a service needs to
+/// deviate from them.
+///
+/// | Setting            | Default                         |
+/// |--------------------|---------------------------------|
+/// | bind addresses     | none (at least one is required) |
+/// | HTTPS redirect     | disabled                        |
+/// | workers            | [`DEFAULT_WORKERS`]             |
+/// | JSON limit         | [`DEFAULT_JSON_LIMIT`]          |
+/// | payload limit      | [`DEFAULT_PAYLOAD_LIMIT`]       |
+/// | keep-alive         | [`KeepAlive::default`]          |
+/// | shutdown timeout   | [`DEFAULT_SHUTDOWN_TIMEOUT`]    |
+/// | middleware         | [`BuiltinMiddleware::default`]  |
+/// | CORS policy        | [`CorsPolicy::permissive`]      |
+/// | close pools        | enabled                         |
+/// | health endpoints   | none                            |
+/// | metrics            | none (`metrics` feature)        |
+///
+/// ```norust
+/// use database_common_lib::actix_extension::HttpServerBuilder;
+/// use include_dir::include_dir;
+///
+/// let server = HttpServerBuilder::new(
+///         || Box::new(|cfg| { cfg.service(...); }),
+///         include_dir!("target/wwwroot"),
+///     )
+///     .bind("127.0.0.1", 8080)
+///     .workers(8)
+///     .json_limit(1024 * 1024)
+///     .build()?;
+/// ```
+pub struct HttpServerBuilder<F> {
+    factory: F,
+    wwwroot: Dir<'static>,
+    addresses: Vec<(String, u16)>,
+    tls_addresses: Vec<(String, u16, TlsConfig)>,
+    redirect_to_https: bool,
+    workers: usize,
+    json_limit: usize,
+    payload_limit: usize,
+    keep_alive: KeepAlive,
+    shutdown_timeout: u64,
+    middleware: BuiltinMiddleware,
+    cors_policy: CorsPolicy,
+    health: Option<Health>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Metrics>,
+    error_format: Option<ErrorFormat>,
+    verbose_errors: Option<bool>,
+    message_catalogs: Option<&'static Dir<'static>>,
+    error_reporters: Vec<Box<dyn FnOnce() + Send>>,
+    startup_hooks: Vec<LifecycleHook>,
+    shutdown_hooks: Vec<LifecycleHook>,
+    close_pools_on_shutdown: bool,
+}
+
+impl<F> HttpServerBuilder<F>
+where
+    F: Fn() -> Box<dyn FnOnce(&mut web::ServiceConfig) + Send + 'static> + Send + Clone + 'static,
+{
+    /// Creates a builder with the default settings.
+    ///
+    /// # Arguments
+    /// * `factory` - A function that configures web service routes and settings
+    /// * `wwwroot` - Embedded static directory containing the files
+    pub fn new(factory: F, wwwroot: Dir<'static>) -> Self {
+        Self {
+            factory,
+            wwwroot,
+            addresses: Vec::new(),
+            tls_addresses: Vec::new(),
+            redirect_to_https: false,
+            workers: DEFAULT_WORKERS,
+            json_limit: DEFAULT_JSON_LIMIT,
+            payload_limit: DEFAULT_PAYLOAD_LIMIT,
+            keep_alive: KeepAlive::default(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            middleware: BuiltinMiddleware::default(),
+            cors_policy: CorsPolicy::default(),
+            health: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            error_format: None,
+            verbose_errors: None,
+            message_catalogs: None,
+            error_reporters: Vec::new(),
+            startup_hooks: Vec::new(),
+            shutdown_hooks: Vec::new(),
+            close_pools_on_shutdown: true,
+        }
+    }
+
+    /// Adds an address to listen on. May be called multiple times to bind several addresses.
+    pub fn bind(mut self, host: impl Into<String>, port: u16) -> Self {
+        self.addresses.push((host.into(), port));
+        self
+    }
+
+    /// Adds an HTTPS address to listen on using the given certificate.
+    ///
+    /// May be combined with [`HttpServerBuilder::bind`] to serve plain HTTP alongside HTTPS.
+    pub fn bind_tls(mut self, host: impl Into<String>, port: u16, tls: TlsConfig) -> Self {
+        self.tls_addresses.push((host.into(), port, tls));
+        self
+    }
+
+    /// Redirects every plain HTTP request to the first HTTPS address with `308 Permanent Redirect`.
+    ///
+    /// The health and metrics endpoints stay reachable over plain HTTP for probes and scrapers.
+    /// Requires at least one [`HttpServerBuilder::bind_tls`] address.
+    pub fn redirect_to_https(mut self, enabled: bool) -> Self {
+        self.redirect_to_https = enabled;
+        self
+    }
+
+    /// Sets the number of worker threads.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the maximum size (in bytes) of JSON request bodies.
+    pub fn json_limit(mut self, limit: usize) -> Self {
+        self.json_limit = limit;
+        self
+    }
+
+    /// Sets the maximum size (in bytes) of raw request payloads.
+    pub fn payload_limit(mut self, limit: usize) -> Self {
+        self.payload_limit = limit;
+        self
+    }
+
+    /// Sets the connection keep-alive behaviour.
+    pub fn keep_alive(mut self, keep_alive: impl Into<KeepAlive>) -> Self {
+        self.keep_alive = keep_alive.into();
+        self
+    }
+
+    /// Sets the graceful shutdown timeout in seconds.
+    pub fn shutdown_timeout(mut self, seconds: u64) -> Self {
+        self.shutdown_timeout = seconds;
+        self
+    }
+
+    /// Replaces the full set of built-in middleware flags.
+    pub fn middleware(mut self, middleware: BuiltinMiddleware) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Enables or disables the request logger.
+    pub fn logger(mut self, enabled: bool) -> Self {
+        self.middleware.logger = enabled;
+        self
+    }
+
+    /// Sets the access log format. JSON lines are logged on the
+    /// [`ACCESS_LOG_TARGET`](crate::actix_extension::request_id::ACCESS_LOG_TARGET) target.
+    pub fn access_log(mut self, format: AccessLogFormat) -> Self {
+        self.middleware.access_log = format;
+        self
+    }
+
+    /// Enables or disables request IDs.
+    pub fn request_id(mut self, enabled: bool) -> Self {
+        self.middleware.request_id = enabled;
+        self
+    }
+
+    /// Enables or disables the CORS middleware.
+    pub fn cors(mut self, enabled: bool) -> Self {
+        self.middleware.cors = enabled;
+        self
+    }
+
+    /// Sets the CORS policy and enables the CORS middleware.
+    ///
+    /// Use [`CorsPolicy::from_env`] to let deployments lock origins down without code changes.
+    pub fn cors_policy(mut self, policy: CorsPolicy) -> Self {
+        self.cors_policy = policy;
+        self.middleware.cors = true;
+        self
+    }
+
+    /// Mounts the liveness and readiness endpoints of `health` on every app.
+    pub fn health(mut self, health: Health) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Records request metrics and serves them in Prometheus text format on the metrics path.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Selects the process-wide error body format.
+    ///
+    /// Shorthand for [`set_error_format`](crate::http_error::problem::set_error_format), applied
+    /// when the server is built.
+    pub fn error_format(mut self, format: ErrorFormat) -> Self {
+        self.error_format = Some(format);
+        self
+    }
+
+    /// Selects whether `5xx` error bodies include internal details for the whole process.
+    ///
+    /// Shorthand for [`set_verbose_errors`](crate::http_error::redaction::set_verbose_errors),
+    /// applied when the server is built. Defaults to the `HTTP_VERBOSE_ERRORS` environment
+    /// variable, or debug builds only.
+    pub fn verbose_errors(mut self, verbose: bool) -> Self {
+        self.verbose_errors = Some(verbose);
+        self
+    }
+
+    /// Loads localized error message catalogs, one `<language>.json` file per language.
+    ///
+    /// Shorthand for [`load_catalogs`](crate::http_error::i18n::load_catalogs), applied when the
+    /// server is built.
+    pub fn message_catalogs(mut self, dir: &'static Dir<'static>) -> Self {
+        self.message_catalogs = Some(dir);
+        self
+    }
+
+    /// Adds a reporter invoked for every `5xx` error response.
+    ///
+    /// Shorthand for
+    /// [`register_error_reporter`](crate::http_error::reporting::register_error_reporter), applied
+    /// when the server is built.
+    pub fn error_reporter<R: ErrorReporter>(mut self, reporter: R) -> Self {
+        self.error_reporters
+            .push(Box::new(move || register_error_reporter(reporter)));
+        self
+    }
+
+    /// Registers an async hook run by [`HttpServerBuilder::run`] before the server binds.
+    ///
+    /// Hooks run in registration order; the first failure aborts startup.
+    pub fn on_startup<H, Fut>(mut self, hook: H) -> Self
+    where
+        H: FnOnce() -> Fut + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + 'static,
+    {
+        self.startup_hooks.push(Box::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Registers an async hook run by [`HttpServerBuilder::run`] after the server has stopped
+    /// and in-flight requests have drained.
+    ///
+    /// Hooks run in registration order; failures are logged and do not stop later hooks.
+    pub fn on_shutdown<H, Fut>(mut self, hook: H) -> Self
+    where
+        H: FnOnce() -> Fut + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + 'static,
+    {
+        self.shutdown_hooks.push(Box::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Sets whether pools created by [`create_pool`](crate::database_connection::create_pool)
+    /// are closed after the shutdown hooks have run.
+    pub fn close_pools_on_shutdown(mut self, enabled: bool) -> Self {
+        self.close_pools_on_shutdown = enabled;
+        self
+    }
+
+    /// Runs the startup hooks, serves until the server stops, then runs the shutdown hooks.
+    ///
+    /// Actix stops the server on `SIGINT`/`SIGTERM`, giving workers the configured
+    /// [shutdown timeout](HttpServerBuilder::shutdown_timeout) to finish in-flight requests.
+    /// Afterwards the shutdown hooks run, library-created pools are closed and the logger is
+    /// flushed.
+    ///
+    /// # Errors
+    /// * A startup hook fails.
+    /// * [`HttpServerBuilder::build`] fails.
+    /// * The server stops with an I/O error.
+    pub async fn run(mut self) -> Result<(), std::io::Error> {
+        let startup_hooks = std::mem::take(&mut self.startup_hooks);
+        let shutdown_hooks = std::mem::take(&mut self.shutdown_hooks);
+        let close_pools = self.close_pools_on_shutdown;
+
+        for hook in startup_hooks {
+            hook()
+                .await
+                .map_err(|e| std::io::Error::other(format!("Startup hook failed: {e:#}")))?;
+        }
+
+        let result = match self.build() {
+            Ok(server) => server.await,
+            Err(e) => Err(e),
+        };
+
+        info!("Server stopped; running shutdown hooks");
+        for hook in shutdown_hooks {
+            if let Err(e) = hook().await {
+                error!("Shutdown hook failed: {e:#}");
+            }
+        }
+        if close_pools {
+            crate::database_connection::close_pools().await;
+        }
+        log::logger().flush();
+
+        result
+    }
+
+    /// Binds the configured addresses and returns the running server.
+    ///
+    /// Lifecycle hooks are only run by [`HttpServerBuilder::run`]; they are ignored here.
+    ///
+    /// # Errors
+    /// * No bind address was configured.
+    /// * HTTPS redirection is enabled without an HTTPS address.
+    /// * The CORS policy allows credentials for any origin.
+    /// * A TLS certificate or key cannot be loaded.
+    /// * Binding any of the addresses fails.
+    pub fn build(self) -> Result<Server, std::io::Error> {
+        if self.addresses.is_empty() && self.tls_addresses.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "no bind address configured; call HttpServerBuilder::bind first",
+            ));
+        }
+        let https_redirect = match (self.redirect_to_https, self.tls_addresses.first()) {
+            (false, _) => None,
+            (true, Some((_, port, _))) => {
+                let mut exempt = Vec::new();
+                if let Some(health) = &self.health {
+                    exempt.extend(health.endpoints().map(String::from));
+                }
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    exempt.push(metrics.endpoint().to_string());
+                }
+                Some(HttpsRedirect {
+                    port: *port,
+                    exempt: exempt.into(),
+                })
+            }
+            (true, None) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "HTTPS redirect requires an HTTPS address; call HttpServerBuilder::bind_tls",
+                ));
+            }
+        };
+
+        if self.middleware.cors {
+            self.cors_policy.validate().map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid CORS policy: {e:#}"),
+                )
+            })?;
+        }
+        if let Some(format) = self.error_format {
+            set_error_format(format);
+        }
+        if let Some(verbose) = self.verbose_errors {
+            set_verbose_errors(verbose);
+        }
+        if let Some(dir) = self.message_catalogs {
+            load_catalogs(dir).map_err(|e| std::io::Error::other(format!("{e:#}")))?;
+        }
+        for register in self.error_reporters {
+            register();
+        }
+
+        let factory = self.factory;
+        let wwwroot = Data::new(self.wwwroot);
+        let json_limit = self.json_limit;
+        let payload_limit = self.payload_limit;
+        let middleware = self.middleware;
+        let cors_policy = self.cors_policy;
+        let context_settings = RequestContextSettings {
+            request_id: middleware.request_id,
+            json_access_log: middleware.logger && middleware.access_log == AccessLogFormat::Json,
+        };
+        let health = self.health;
+        #[cfg(feature = "metrics")]
+        let metrics = self.metrics;
+
+        let mut server = HttpServer::new(move || {
+            let config_fn = factory();
+            let app = App::new()
+                .wrap(
+                    ErrorHandlers::new()
+                        .handler(StatusCode::PAYLOAD_TOO_LARGE, payload_too_large_handler),
+                )
+                .wrap(Condition::new(middleware.cors, cors_policy.build_cors()))
+                .wrap(Condition::new(
+                    https_redirect.is_some(),
+                    from_fn(redirect_to_https),
+                ))
+                // Outside of CORS and the redirect so the responses they produce are logged too
+                .wrap(Condition::new(
+                    middleware.logger && middleware.access_log == AccessLogFormat::Text,
+                    middleware::Logger::default(),
+                ))
+                .wrap(from_fn(request_context))
+                .app_data(https_redirect.clone())
+                .app_data(context_settings)
+                .app_data(
+                    web::JsonConfig::default()
+                        .limit(json_limit)
+                        .error_handler(json_error_handler),
+                )
+                .app_data(web::PathConfig::default().error_handler(path_error_handler))
+                .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+                .app_data(web::FormConfig::default().error_handler(form_error_handler))
+                .app_data(web::PayloadConfig::new(payload_limit))
+                .configure(|cfg| config_fn(cfg))
+                .configure(|cfg| {
+                    if let Some(health) = &health {
+                        health.configure(cfg);
+                    }
+                });
+            #[cfg(feature = "metrics")]
+            let app = crate::actix_extension::metrics::install(app, metrics.clone());
+            app.configure_routes(wwwroot.clone())
+        })
+        .workers(self.workers)
+        .keep_alive(self.keep_alive)
+        .shutdown_timeout(self.shutdown_timeout);
+
+        for (host, port) in &self.addresses {
+            server = server.bind((host.as_str(), *port))?;
+        }
+        for (host, port, tls) in &self.tls_addresses {
+            let config = tls
+                .server_config()
+                .map_err(|e| std::io::Error::other(format!("{e:#}")))?;
+            server = server.bind_rustls_0_23((host.as_str(), *port), config)?;
+        }
+
+        Ok(server.run())
+    }
+}
+
+/// Converts `web::Json` failures into the library's error body.
+fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    extractor_error(ExtractorError::from_json(&err))
+}
+
+/// Converts `web::Path` failures into the library's error body.
+fn path_error_handler(err: PathError, req: &HttpRequest) -> actix_web::Error {
+    extractor_error(ExtractorError::from_path(&err, req))
+}
+
+/// Converts `web::Query` failures into the library's error body.
+fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    extractor_error(ExtractorError::from_query(&err))
+}
+
+/// Converts `web::Form` failures into the library's error body.
+fn form_error_handler(err: UrlencodedError, _req: &HttpRequest) -> actix_web::Error {
+    extractor_error(ExtractorError::from_form(&err))
+}
+
+fn extractor_error(err: ExtractorError) -> actix_web::Error {
+    debug!("Failed to extract request {}: {}", err.source, err);
+    crate::http_error::Error::coded(err).into()
+}
+
+/// Replaces the plain-text `413` produced by the `web::Bytes`/`String` extractors, which have no
+/// error handler hook, with the library's error body.
+fn payload_too_large_handler<B>(
+    res: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let rendered = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+    if rendered {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    let (req, _) = res.into_parts();
+    let error = crate::http_error::Error::coded(ExtractorError::payload_too_large(
+        "request body exceeds the configured limit",
+    ));
+    let res = ServiceResponse::new(req, error.error_response());
+    Ok(ErrorHandlerResponse::Response(res.map_into_right_body()))
+}
+
+/// Port of the HTTPS listener plain HTTP requests are redirected to.
+#[derive(Clone, Debug)]
+struct HttpsRedirect {
+    port: u16,
+    /// Operational endpoints served over plain HTTP as well.
+    exempt: Arc<[String]>,
+}
+
+/// Redirects requests received on a plain HTTP listener to the HTTPS listener.
+async fn redirect_to_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let redirect = req
+        .app_data::<Option<HttpsRedirect>>()
+        .and_then(Option::as_ref);
+    let port = match redirect {
+        Some(redirect)
+            if !req.request().app_config().secure()
+                && !redirect.exempt.iter().any(|path| path == req.path()) =>
+        {
+            redirect.port
+        }
+        _ => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+
+    let location = {
+        let info = req.connection_info();
+        let path = req
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        https_location(info.host(), port, path)
+    };
+
+    let response = HttpResponse::PermanentRedirect()
+        .insert_header((LOCATION, location))
+        .finish();
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+/// URL of `path` on the HTTPS listener at `port` of `host`, omitting the default port.
+fn https_location(host: &str, port: u16, path: &str) -> String {
+    let host = strip_port(host);
+    match port {
+        443 => format!("https://{host}{path}"),
+        port => format!("https://{host}:{port}{path}"),
+    }
+}
+
+/// Removes a trailing `:port` from a `Host` value, leaving bracketed IPv6 addresses intact.
+fn strip_port(host: &str) -> &str {
+    if host.ends_with(']') {
+        return host;
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{TestRequest, call_service, init_service};
+
+    #[test]
+    fn strip_port_keeps_names_and_ipv6_addresses() {
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+    }
+
+    #[test]
+    fn https_location_omits_the_default_port() {
+        assert_eq!(
+            https_location("example.com:8080", 443, "/a?b=c"),
+            "https://example.com/a?b=c"
+        );
+        assert_eq!(
+            https_location("example.com:8080", 8443, "/"),
+            "https://example.com:8443/"
+        );
+        assert_eq!(
+            https_location("[::1]:8080", 8443, "/a"),
+            "https://[::1]:8443/a"
+        );
+    }
+
+    #[actix_web::test]
+    async fn operational_endpoints_are_not_redirected() {
+        let redirect = HttpsRedirect {
+            port: 8443,
+            exempt: vec!["/live".to_string(), "/metrics".to_string()].into(),
+        };
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(redirect_to_https))
+                .app_data(Some(redirect))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for path in ["/live", "/metrics"] {
+            let request = TestRequest::get().uri(path).to_request();
+            assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+        }
+
+        let request = TestRequest::get()
+            .uri("/api/items?page=2")
+            .insert_header(("host", "example.com:8080"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://example.com:8443/api/items?page=2"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use anyhow::{Context, Result, anyhow};
+use log::{error, info, warn};
+use rustls::ServerConfig;
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Where the PEM-encoded certificate chain and private key are loaded from.
+#[derive(Clone)]
+pub enum TlsSource {
+    /// Certificate chain and private key PEM files on disk.
+    Files { cert: PathBuf, key: PathBuf },
+    /// Certificate chain and private key PEM contents held in memory.
+    Pem { cert: Vec<u8>, key: Vec<u8> },
+}
+
+impl fmt::Debug for TlsSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Files { cert, key } => f
+                .debug_struct("Files")
+                .field("cert", cert)
+                .field("key", key)
+                .finish(),
+            // Never print key material.
+            Self::Pem { .. } => f.write_str("Pem { .. }"),
+        }
+    }
+}
+
+/// HTTPS listener settings for [`HttpServerBuilder::bind_tls`](super::HttpServerBuilder::bind_tls).
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    source: TlsSource,
+    reload_on_sighup: bool,
+}
+
+impl TlsConfig {
+    /// Loads the certificate chain and private key from PEM files.
+    ///
+    /// The files are re-read on `SIGHUP` (Unix only) so renewed certificates can be picked up
+    /// without a restart; disable with [`TlsConfig::reload_on_sighup`].
+    pub fn from_files(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self {
+            source: TlsSource::Files {
+                cert: cert.into(),
+                key: key.into(),
+            },
+            reload_on_sighup: true,
+        }
+    }
+
+    /// Uses in-memory PEM contents for the certificate chain and private key.
+    pub fn from_pem(cert: impl Into<Vec<u8>>, key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            source: TlsSource::Pem {
+                cert: cert.into(),
+                key: key.into(),
+            },
+            reload_on_sighup: false,
+        }
+    }
+
+    /// Enables or disables reloading the certificate files on `SIGHUP`.
+    ///
+    /// Has no effect for [`TlsSource::Pem`], which has nothing to re-read.
+    pub fn reload_on_sighup(mut self, enabled: bool) -> Self {
+        self.reload_on_sighup = enabled;
+        self
+    }
+
+    /// Returns the configured certificate source.
+    pub fn source(&self) -> &TlsSource {
+        &self.source
+    }
+
+    /// Loads the certificate and builds a rustls server configuration.
+    ///
+    /// When reloading is enabled and the source is on disk, a background task is spawned on
+    /// the current Tokio runtime that swaps in the re-read certificate on every `SIGHUP`.
+    ///
+    /// # Errors
+    /// * The certificate or key cannot be read or parsed.
+    /// * The key type is not supported.
+    pub(crate) fn server_config(&self) -> Result<ServerConfig> {
+        let resolver = Arc::new(ReloadableCertResolver::new(load_certified_key(
+            &self.source,
+        )?));
+
+        if self.reload_on_sighup && matches!(self.source, TlsSource::Files { .. }) {
+            spawn_sighup_reload(resolver.clone(), self.source.clone());
+        }
+
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .context("Failed to select TLS protocol versions")?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+        Ok(config)
+    }
+}
+
+/// Certificate resolver whose certificate can be swapped while the server is running.
+struct ReloadableCertResolver {
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadableCertResolver {
+    fn new(key: CertifiedKey) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(key)),
+        }
+    }
+
+    fn replace(&self, key: CertifiedKey) {
+        match self.current.write() {
+            Ok(mut current) => *current = Arc::new(key),
+            Err(poisoned) => *poisoned.into_inner() = Arc::new(key),
+        }
+    }
+}
+
+impl fmt::Debug for ReloadableCertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReloadableCertResolver")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        match self.current.read() {
+            Ok(current) => Some(current.clone()),
+            Err(poisoned) => Some(poisoned.into_inner().clone()),
+        }
+    }
+}
+
+/// Reads and parses the certificate chain and private key from `source`.
+fn load_certified_key(source: &TlsSource) -> Result<CertifiedKey> {
+    let (certs, key) = match source {
+        TlsSource::Files { cert, key } => {
+            let certs = CertificateDer::pem_file_iter(cert)
+                .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                .with_context(|| format!("Failed to read certificate chain {}", cert.display()))?;
+            let key = PrivateKeyDer::from_pem_file(key)
+                .with_context(|| format!("Failed to read private key {}", key.display()))?;
+            (certs, key)
+        }
+        TlsSource::Pem { cert, key } => {
+            let certs = CertificateDer::pem_slice_iter(cert)
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to parse certificate chain")?;
+            let key = PrivateKeyDer::from_pem_slice(key).context("Failed to parse private key")?;
+            (certs, key)
+        }
+    };
+
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found in {:?}", source));
+    }
+
+    let signing_key =
+        ring::sign::any_supported_type(&key).context("Unsupported private key type")?;
+    Ok(CertifiedKey::new(certs, signing_key))
+}
+
+/// Re-reads the certificate on every `SIGHUP`. Failed reloads keep the current certificate.
+#[cfg(unix)]
+fn spawn_sighup_reload(resolver: Arc<ReloadableCertResolver>, source: TlsSource) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    if tokio::runtime::Handle::try_current().is_err() {
+        warn!("No Tokio runtime available; TLS certificate reload on SIGHUP is disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                error!("Failed to listen for SIGHUP; TLS certificate reload is disabled: {e}");
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            match load_certified_key(&source) {
+                Ok(key) => {
+                    resolver.replace(key);
+                    info!("Reloaded TLS certificate from {:?}", source);
+                }
+                Err(e) => error!("Failed to reload TLS certificate, keeping current one: {e:?}"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_sighup_reload(_resolver: Arc<ReloadableCertResolver>, _source: TlsSource) {
+    warn!("TLS certificate reload on SIGHUP is only supported on Unix");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// PEM certificate and key of a fresh self-signed certificate.
+    fn self_signed() -> (String, String) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        (certified.cert.pem(), certified.signing_key.serialize_pem())
+    }
+
+    /// Writes `cert` and `key` to fresh files in the temporary directory.
+    fn write_files(cert: &str, key: &str) -> TlsSource {
+        let dir = std::env::temp_dir().join(format!("tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = TlsSource::Files {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+        };
+        rewrite_files(&source, cert, key);
+        source
+    }
+
+    fn rewrite_files(source: &TlsSource, cert: &str, key: &str) {
+        let TlsSource::Files {
+            cert: cert_path,
+            key: key_path,
+        } = source
+        else {
+            unreachable!()
+        };
+        std::fs::write(cert_path, cert).unwrap();
+        std::fs::write(key_path, key).unwrap();
+    }
+
+    fn remove_files(source: &TlsSource) {
+        if let TlsSource::Files { cert, .. } = source {
+            std::fs::remove_dir_all(cert.parent().unwrap()).unwrap();
+        }
+    }
+
+    fn current_cert(resolver: &ReloadableCertResolver) -> CertificateDer<'static> {
+        resolver.current.read().unwrap().cert[0].clone()
+    }
+
+    #[test]
+    fn certificates_load_from_files_and_memory() {
+        let (cert, key) = self_signed();
+        let from_pem = load_certified_key(&TlsSource::Pem {
+            cert: cert.clone().into(),
+            key: key.clone().into(),
+        })
+        .unwrap();
+        let source = write_files(&cert, &key);
+        let from_files = load_certified_key(&source).unwrap();
+        remove_files(&source);
+        assert_eq!(from_pem.cert, from_files.cert);
+
+        let missing_cert = TlsSource::Pem {
+            cert: Vec::new(),
+            key: key.into(),
+        };
+        assert!(load_certified_key(&missing_cert).is_err());
+    }
+
+    #[test]
+    fn replace_swaps_the_served_certificate() {
+        let (cert, key) = self_signed();
+        let (renewed_cert, renewed_key) = self_signed();
+        let resolver = ReloadableCertResolver::new(
+            load_certified_key(&TlsSource::Pem {
+                cert: cert.into(),
+                key: key.into(),
+            })
+            .unwrap(),
+        );
+        let renewed = load_certified_key(&TlsSource::Pem {
+            cert: renewed_cert.into(),
+            key: renewed_key.into(),
+        })
+        .unwrap();
+        let expected = renewed.cert[0].clone();
+        resolver.replace(renewed);
+        assert_eq!(current_cert(&resolver), expected);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sighup_reloads_the_certificate_files() {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        // Installs the handler up front so no SIGHUP can terminate the test process
+        let _hangup = signal(SignalKind::hangup()).unwrap();
+        let (cert, key) = self_signed();
+        let source = write_files(&cert, &key);
+        let resolver = Arc::new(ReloadableCertResolver::new(
+            load_certified_key(&source).unwrap(),
+        ));
+        spawn_sighup_reload(resolver.clone(), source.clone());
+
+        let (renewed_cert, renewed_key) = self_signed();
+        rewrite_files(&source, &renewed_cert, &renewed_key);
+        let expected = load_certified_key(&source).unwrap().cert[0].clone();
+        for _ in 0..100 {
+            std::process::Command::new("kill")
+                .args(["-HUP", &std::process::id().to_string()])
+                .status()
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            if current_cert(&resolver) == expected {
+                remove_files(&source);
+                return;
+            }
+        }
+        panic!("certificate was not reloaded on SIGHUP");
+    }
+}
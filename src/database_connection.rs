@@ -1,18 +1,37 @@
+mod cache;
+pub mod file;
+pub mod registry;
+pub mod reload;
+pub mod remote;
+pub mod retry;
+
+pub use file::{ConfigFormat, set_config_file};
+pub use registry::{DatabaseRegistry, NamedDatabase};
+pub use reload::{DEFAULT_RELOAD_INTERVAL, ReloadablePool};
+pub use remote::{
+    DEFAULT_CACHE_MAX_STALENESS, DEFAULT_REMOTE_CONFIG_URL, RemoteConfigOptions,
+    ping_remote_config, set_remote_config_options,
+};
+pub use retry::RetryPolicy;
+
 use anyhow::{Result, anyhow};
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
-use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions, MySqlSslMode};
 use sqlx::{ConnectOptions, MySqlPool};
-use std::sync::OnceLock;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 /// Global database name, set once at process start via [`set_database_name`].
 /// `OnceLock<String>` is sufficient here — the value is written once and only
 /// read afterwards, so no additional locking is required.
 static DATABASE_NAME: OnceLock<String> = OnceLock::new();
 
-/// Remote URL used to fetch production configuration when no local env vars
-/// are provided.
-const REMOTE_CONFIG_URL: &str = "https://lib.mardens.com/config.json";
+/// Pools created by [`create_pool`], closed by [`close_pools`] on shutdown.
+static CREATED_POOLS: Mutex<Vec<MySqlPool>> = Mutex::new(Vec::new());
 
 // ---------------------------------------------------------------------------
 // Env var names (all optional). See `DatabaseConnectionData::get` for precedence.
@@ -25,12 +44,25 @@ const ENV_HASH: &str = "DB_HASH";
 const ENV_FM_USER: &str = "DB_FILEMAKER_USER";
 const ENV_FM_PASSWORD: &str = "DB_FILEMAKER_PASSWORD";
 const ENV_DB_NAME: &str = "DB_NAME";
+const ENV_POOL_MAX: &str = "DB_POOL_MAX";
+const ENV_POOL_MIN: &str = "DB_POOL_MIN";
+const ENV_POOL_ACQUIRE_TIMEOUT: &str = "DB_POOL_ACQUIRE_TIMEOUT";
+const ENV_POOL_IDLE_TIMEOUT: &str = "DB_POOL_IDLE_TIMEOUT";
+const ENV_POOL_MAX_LIFETIME: &str = "DB_POOL_MAX_LIFETIME";
+const ENV_POOL_TEST_BEFORE_ACQUIRE: &str = "DB_POOL_TEST_BEFORE_ACQUIRE";
+const ENV_POOL_LAZY: &str = "DB_POOL_LAZY";
+const ENV_CONNECT_ATTEMPTS: &str = "DB_CONNECT_ATTEMPTS";
+const ENV_CONNECT_DEADLINE: &str = "DB_CONNECT_DEADLINE";
+const ENV_SSL_MODE: &str = "DB_SSL_MODE";
+const ENV_SSL_CA: &str = "DB_SSL_CA";
+const ENV_SSL_CERT: &str = "DB_SSL_CERT";
+const ENV_SSL_KEY: &str = "DB_SSL_KEY";
 
 /// Database connection configuration.
 ///
 /// Contains credentials and connection details for both MySQL and the
 /// Filemaker integration.
-#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
 pub struct DatabaseConnectionData {
     /// MySQL host address.
     pub host: String,
@@ -44,10 +76,68 @@ pub struct DatabaseConnectionData {
     pub hash: String,
     /// MySQL server port. `None` lets sqlx use the MySQL default (3306).
     pub port: Option<u16>,
+    /// Connection pool sizing and timeouts. Optional in the remote config JSON.
+    #[serde(default)]
+    pub pool: PoolSettings,
+    /// TLS settings of the MySQL connection. Optional in the remote config JSON.
+    #[serde(default)]
+    pub ssl: SslSettings,
+}
+
+/// Connection pool tuning applied by [`create_pool`].
+///
+/// Every field is optional; `None` keeps the sqlx default.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct PoolSettings {
+    /// Maximum number of connections (sqlx default: 10).
+    pub max_connections: Option<u32>,
+    /// Connections kept open even when idle (sqlx default: 0).
+    pub min_connections: Option<u32>,
+    /// Seconds to wait for a connection before failing (sqlx default: 30).
+    pub acquire_timeout_secs: Option<u64>,
+    /// Seconds after which an idle connection is closed; `0` disables (sqlx default: 600).
+    pub idle_timeout_secs: Option<u64>,
+    /// Seconds after which a connection is replaced; `0` disables (sqlx default: 1800).
+    pub max_lifetime_secs: Option<u64>,
+    /// Whether connections are pinged before being handed out (sqlx default: `true`).
+    pub test_before_acquire: Option<bool>,
+    /// Create the pool without connecting, so the server can start before MySQL is reachable.
+    /// Connections are opened on first use; pair with a readiness check such as
+    /// [`MySqlPing`](crate::actix_extension::health::MySqlPing). Default: `false`.
+    pub lazy: Option<bool>,
+    /// Retries of the initial connection of non-lazy pools.
+    pub retry: RetryPolicy,
+}
+
+impl PoolSettings {
+    /// Returns sqlx pool options with these settings applied.
+    pub fn pool_options(&self) -> MySqlPoolOptions {
+        let mut options = MySqlPoolOptions::new();
+        if let Some(max) = self.max_connections {
+            options = options.max_connections(max);
+        }
+        if let Some(min) = self.min_connections {
+            options = options.min_connections(min);
+        }
+        if let Some(secs) = self.acquire_timeout_secs {
+            options = options.acquire_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.idle_timeout_secs {
+            options = options.idle_timeout((secs > 0).then(|| Duration::from_secs(secs)));
+        }
+        if let Some(secs) = self.max_lifetime_secs {
+            options = options.max_lifetime((secs > 0).then(|| Duration::from_secs(secs)));
+        }
+        if let Some(test) = self.test_before_acquire {
+            options = options.test_before_acquire(test);
+        }
+        options
+    }
 }
 
 /// Filemaker database authentication credentials.
-#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
 pub struct FilemakerCredentials {
     /// Filemaker username.
     pub username: String,
@@ -55,31 +145,143 @@ pub struct FilemakerCredentials {
     pub password: String,
 }
 
+/// TLS settings of the MySQL connection, applied by [`create_pool`].
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct SslSettings {
+    /// Whether and how strictly TLS is used. `None` keeps the sqlx default,
+    /// [`SslMode::Preferred`].
+    pub mode: Option<SslMode>,
+    /// PEM file of the CA certificate(s) the server certificate is verified against.
+    pub ca: Option<PathBuf>,
+    /// PEM file of the client certificate, for servers requiring X.509 authentication.
+    pub client_cert: Option<PathBuf>,
+    /// PEM file of the client certificate's private key.
+    pub client_key: Option<PathBuf>,
+}
+
+impl SslSettings {
+    /// Applies these settings to `options`.
+    ///
+    /// # Errors
+    /// Returns an error if only one of `client_cert` and `client_key` is set.
+    pub fn apply(&self, mut options: MySqlConnectOptions) -> Result<MySqlConnectOptions> {
+        if let Some(mode) = self.mode {
+            options = options.ssl_mode(mode.into());
+        }
+        if let Some(ca) = &self.ca {
+            options = options.ssl_ca(ca);
+        }
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                options = options.ssl_client_cert(cert).ssl_client_key(key);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(anyhow!(
+                    "Both the MySQL client certificate ({ENV_SSL_CERT}) and key ({ENV_SSL_KEY}) must be set"
+                ));
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// MySQL TLS mode, named like the MySQL client's `--ssl-mode` option.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    /// Never use TLS.
+    Disabled,
+    /// Use TLS if the server supports it.
+    Preferred,
+    /// Require TLS without verifying the server certificate.
+    Required,
+    /// Require TLS and verify the server certificate against the CA.
+    VerifyCa,
+    /// Like [`SslMode::VerifyCa`], and check the certificate matches the host name.
+    VerifyIdentity,
+}
+
+impl From<SslMode> for MySqlSslMode {
+    fn from(mode: SslMode) -> Self {
+        match mode {
+            SslMode::Disabled => MySqlSslMode::Disabled,
+            SslMode::Preferred => MySqlSslMode::Preferred,
+            SslMode::Required => MySqlSslMode::Required,
+            SslMode::VerifyCa => MySqlSslMode::VerifyCa,
+            SslMode::VerifyIdentity => MySqlSslMode::VerifyIdentity,
+        }
+    }
+}
+
+impl FromStr for SslMode {
+    type Err = String;
+
+    /// Parses `verify-ca`, `VERIFY_CA` and the like.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "disabled" => Ok(SslMode::Disabled),
+            "preferred" => Ok(SslMode::Preferred),
+            "required" => Ok(SslMode::Required),
+            "verify-ca" => Ok(SslMode::VerifyCa),
+            "verify-identity" => Ok(SslMode::VerifyIdentity),
+            _ => Err(
+                "expected disabled, preferred, required, verify-ca or verify-identity".to_string(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SslMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SslMode::Disabled => "disabled",
+            SslMode::Preferred => "preferred",
+            SslMode::Required => "required",
+            SslMode::VerifyCa => "verify-ca",
+            SslMode::VerifyIdentity => "verify-identity",
+        })
+    }
+}
+
 impl DatabaseConnectionData {
     /// Loads the database connection configuration.
     ///
     /// Resolution order:
     /// 1. **Base config**
+    ///    - When a file is set with [`set_config_file`] or `DB_CONFIG_FILE`: read with
+    ///      [`DatabaseConnectionData::from_file`].
     ///    - Debug builds: [`DatabaseConnectionData::default`] (empty fields).
-    ///    - Release builds: fetched from [`REMOTE_CONFIG_URL`].
+    ///    - Release builds: fetched from the remote config endpoint, with
+    ///      the certificate and signature checks of [`RemoteConfigOptions`].
     /// 2. **Environment overrides** — any of the following variables that are
     ///    set will replace the corresponding field on the base config:
     ///    `DB_HOST`, `DB_USER`, `DB_PASSWORD`, `DB_PORT`, `DB_HASH`,
-    ///    `DB_FILEMAKER_USER`, `DB_FILEMAKER_PASSWORD`.
+    ///    `DB_FILEMAKER_USER`, `DB_FILEMAKER_PASSWORD`, and the [`PoolSettings`]
+    ///    variables `DB_POOL_MAX`, `DB_POOL_MIN`, `DB_POOL_ACQUIRE_TIMEOUT`,
+    ///    `DB_POOL_IDLE_TIMEOUT`, `DB_POOL_MAX_LIFETIME`, `DB_POOL_TEST_BEFORE_ACQUIRE`,
+    ///    `DB_POOL_LAZY`, `DB_CONNECT_ATTEMPTS`, `DB_CONNECT_DEADLINE`,
+    ///    and the [`SslSettings`] variables `DB_SSL_MODE`, `DB_SSL_CA`, `DB_SSL_CERT`,
+    ///    `DB_SSL_KEY`.
     /// 3. **Validation** — in debug builds, `host`, `user`, and `password`
     ///    must be non-empty after env-var overlay; otherwise an error naming
     ///    the missing variables is returned.
     ///
     /// # Errors
-    /// * Remote fetch fails (release only).
+    /// * Remote fetch fails, or the certificate or signature checks fail, and no recent enough
+    ///   cached copy exists (release only).
     /// * JSON parsing of the remote response fails (release only).
-    /// * `DB_PORT` is set but cannot be parsed as a `u16`.
+    /// * The config file cannot be read or parsed.
+    /// * `DB_PORT`, `DB_SSL_MODE` or a `DB_POOL_*` variable is set but cannot be parsed.
     /// * Required credentials are missing in debug builds.
     pub async fn get() -> Result<Self> {
-        let mut config = if cfg!(debug_assertions) {
+        let mut config = if let Some(path) = file::config_file() {
+            DatabaseConnectionData::from_file(path)?
+        } else if cfg!(debug_assertions) {
             DatabaseConnectionData::default()
         } else {
-            fetch_remote_config().await?
+            remote::fetch_remote_config().await?
         };
 
         apply_env_overrides(&mut config)?;
@@ -99,19 +301,10 @@ impl DatabaseConnectionData {
     }
 }
 
-/// Fetches the remote production configuration JSON.
-async fn fetch_remote_config() -> Result<DatabaseConnectionData> {
-    use reqwest::Client;
-    let client = Client::builder().danger_accept_invalid_certs(true).build()?;
-    let response = client.get(REMOTE_CONFIG_URL).send().await?;
-    let credentials = response.json::<DatabaseConnectionData>().await?;
-    Ok(credentials)
-}
-
 /// Overlays any set environment variables onto `config`.
 ///
 /// Only fields whose env var is present are modified. Returns an error if
-/// `DB_PORT` is set but not a valid `u16`.
+/// `DB_PORT`, `DB_SSL_MODE` or a `DB_POOL_*` variable is set but cannot be parsed.
 fn apply_env_overrides(config: &mut DatabaseConnectionData) -> Result<()> {
     if let Ok(v) = std::env::var(ENV_HOST) {
         config.host = v;
@@ -131,15 +324,71 @@ fn apply_env_overrides(config: &mut DatabaseConnectionData) -> Result<()> {
     if let Ok(v) = std::env::var(ENV_FM_PASSWORD) {
         config.filemaker.password = v;
     }
-    if let Ok(v) = std::env::var(ENV_PORT) {
-        let port: u16 = v
-            .parse()
-            .map_err(|e| anyhow!("{ENV_PORT} must be a valid u16 (got {v:?}): {e}"))?;
+    if let Some(port) = parse_env(ENV_PORT, "u16")? {
         config.port = Some(port);
     }
+
+    let pool = &mut config.pool;
+    if let Some(max) = parse_env(ENV_POOL_MAX, "u32")? {
+        pool.max_connections = Some(max);
+    }
+    if let Some(min) = parse_env(ENV_POOL_MIN, "u32")? {
+        pool.min_connections = Some(min);
+    }
+    if let Some(secs) = parse_env(ENV_POOL_ACQUIRE_TIMEOUT, "number of seconds")? {
+        pool.acquire_timeout_secs = Some(secs);
+    }
+    if let Some(secs) = parse_env(ENV_POOL_IDLE_TIMEOUT, "number of seconds")? {
+        pool.idle_timeout_secs = Some(secs);
+    }
+    if let Some(secs) = parse_env(ENV_POOL_MAX_LIFETIME, "number of seconds")? {
+        pool.max_lifetime_secs = Some(secs);
+    }
+    if let Some(test) = parse_env(ENV_POOL_TEST_BEFORE_ACQUIRE, "bool (true/false)")? {
+        pool.test_before_acquire = Some(test);
+    }
+    if let Some(lazy) = parse_env(ENV_POOL_LAZY, "bool (true/false)")? {
+        pool.lazy = Some(lazy);
+    }
+    if let Some(attempts) = parse_env(ENV_CONNECT_ATTEMPTS, "u32")? {
+        pool.retry.max_attempts = attempts;
+    }
+    if let Some(secs) = parse_env(ENV_CONNECT_DEADLINE, "number of seconds")? {
+        pool.retry.deadline_secs = Some(secs);
+    }
+
+    let ssl = &mut config.ssl;
+    if let Some(mode) = parse_env(ENV_SSL_MODE, "SSL mode")? {
+        ssl.mode = Some(mode);
+    }
+    if let Ok(v) = std::env::var(ENV_SSL_CA) {
+        ssl.ca = Some(v.into());
+    }
+    if let Ok(v) = std::env::var(ENV_SSL_CERT) {
+        ssl.client_cert = Some(v.into());
+    }
+    if let Ok(v) = std::env::var(ENV_SSL_KEY) {
+        ssl.client_key = Some(v.into());
+    }
     Ok(())
 }
 
+/// Parses the env var `name`, if set, describing the expected value as `expected` on failure.
+fn parse_env<T>(name: &str, expected: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(v) => v
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| anyhow!("{name} must be a valid {expected} (got {v:?}): {e}")),
+        Err(_) => Ok(None),
+    }
+}
+
 /// Ensures required MySQL credentials are present in debug builds.
 fn validate_debug_config(config: &DatabaseConnectionData) -> Result<()> {
     let mut missing = Vec::new();
@@ -165,29 +414,85 @@ fn validate_debug_config(config: &DatabaseConnectionData) -> Result<()> {
 /// Creates a MySQL connection pool from `data`.
 ///
 /// The global database name (set via [`set_database_name`]) is used as the
-/// target database. Port defaults to MySQL's 3306 when `data.port` is `None`.
+/// target database. Port defaults to MySQL's 3306 when `data.port` is `None`,
+/// `data.ssl` configures TLS and `data.pool` sizes the pool and controls connection retries
+/// and lazy connection.
+///
+/// The pool is tracked so [`close_pools`] can drain it when the server shuts down.
 ///
 /// # Errors
 /// * The database name has not been set.
-/// * Connection to MySQL fails.
+/// * Connection to MySQL fails after the configured retries.
 pub async fn create_pool(data: &DatabaseConnectionData) -> Result<MySqlPool> {
-    debug!("Creating MySQL connection pool");
     let db = get_database_name()?;
+    create_pool_with_database(data, &db).await
+}
+
+/// Creates a MySQL connection pool from `data` targeting `database`, ignoring the global
+/// database name.
+///
+/// The pool is tracked so [`close_pools`] can drain it when the server shuts down.
+///
+/// # Errors
+/// * Connection to MySQL fails after the configured retries.
+pub async fn create_pool_with_database(
+    data: &DatabaseConnectionData,
+    database: &str,
+) -> Result<MySqlPool> {
+    debug!("Creating MySQL connection pool for {database}");
     let mut options = MySqlConnectOptions::new()
         .log_statements(log::LevelFilter::Trace)
         .host(&data.host)
         .username(&data.user)
         .password(&data.password)
-        .database(&db);
+        .database(database);
 
     if let Some(port) = data.port {
         options = options.port(port);
     }
+    let options = data.ssl.apply(options)?;
 
-    let pool = MySqlPoolOptions::new().connect_with(options).await?;
+    let pool_options = data.pool.pool_options();
+    let pool = if data.pool.lazy.unwrap_or(false) {
+        pool_options.connect_lazy_with(options)
+    } else {
+        retry::connect_with_retry(&pool_options, &options, &data.pool.retry).await?
+    };
+    track_pool(&pool);
     Ok(pool)
 }
 
+/// Records `pool` so it is closed by [`close_pools`].
+fn track_pool(pool: &MySqlPool) {
+    let mut pools = CREATED_POOLS.lock().unwrap_or_else(|e| e.into_inner());
+    pools.retain(|p| !p.is_closed());
+    pools.push(pool.clone());
+}
+
+/// Returns the open pools created by this library.
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+pub(crate) fn tracked_pools() -> Vec<MySqlPool> {
+    let pools = CREATED_POOLS.lock().unwrap_or_else(|e| e.into_inner());
+    pools.iter().filter(|p| !p.is_closed()).cloned().collect()
+}
+
+/// Closes every pool created by this library, waiting for checked-out connections to be
+/// returned.
+///
+/// Called automatically after the server stops when it is started with
+/// [`HttpServerBuilder::run`](crate::actix_extension::HttpServerBuilder::run).
+pub async fn close_pools() {
+    registry::clear();
+    let pools = std::mem::take(&mut *CREATED_POOLS.lock().unwrap_or_else(|e| e.into_inner()));
+    if pools.is_empty() {
+        return;
+    }
+    debug!("Closing {} MySQL connection pool(s)", pools.len());
+    for pool in pools {
+        pool.close().await;
+    }
+}
+
 /// Sets the global database name.
 ///
 /// Must be called once before [`create_pool`] or [`DatabaseConnectionData::get_pool`].
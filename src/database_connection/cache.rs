@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Remote config as last fetched, written by [`store`] and read back by [`load`].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedConfig {
+    /// Unix time of the fetch, in seconds.
+    pub fetched_at: u64,
+    /// Signature header of the response, if it had one.
+    pub signature: Option<String>,
+    /// Response body, kept verbatim so the signature can be checked again.
+    pub body: String,
+}
+
+impl CachedConfig {
+    /// Cache entry for a body fetched now.
+    pub fn new(body: String, signature: Option<String>) -> Self {
+        Self {
+            fetched_at: unix_now(),
+            signature,
+            body,
+        }
+    }
+
+    /// Time elapsed since the fetch.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.fetched_at))
+    }
+}
+
+/// Age after which leftover temporary files of crashed writers are removed.
+const STALE_TEMPORARY_AGE: Duration = Duration::from_secs(3_600);
+
+/// Replaces the cache at `path` with `cached`. On Unix the file is only accessible to the
+/// current user (`0600`).
+pub(crate) fn store(path: &Path, cached: &CachedConfig) -> Result<()> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(dir) = dir {
+        fs::create_dir_all(dir)?;
+    }
+    let file_name = path
+        .file_name()
+        .context("Cache path has no file name")?
+        .to_string_lossy();
+    remove_stale_temporaries(dir.unwrap_or(Path::new(".")), &file_name);
+
+    // Written next to the cache then renamed, so a crash never leaves a truncated cache. The
+    // name is unique and `create_new` refuses existing files, so a link planted at a
+    // predictable name can never redirect the write
+    let temporary = path.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        uuid::Uuid::new_v4().simple()
+    ));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temporary)?;
+    let written = (|| {
+        // The mode is subject to the umask
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(&serde_json::to_vec(cached)?)?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+/// Removes temporary files of `file_name` in `dir` left behind by writers that crashed.
+fn remove_stale_temporaries(dir: &Path, file_name: &str) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let prefix = format!(".{file_name}.");
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with(&prefix) || !name.ends_with(".tmp") {
+            continue;
+        }
+        // Not followed, so a link is removed rather than its target
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() >= STALE_TEMPORARY_AGE);
+        if stale && let Err(e) = fs::remove_file(entry.path()) {
+            warn!(
+                "Failed to remove stale cache file {}: {e}",
+                entry.path().display()
+            );
+        }
+    }
+}
+
+/// Reads the cache at `path`.
+pub(crate) fn load(path: &Path) -> Result<CachedConfig> {
+    let contents = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            warn!(
+                "Cached remote config {} is accessible to other users (mode {:o})",
+                path.display(),
+                mode & 0o777
+            );
+        }
+    }
+    serde_json::from_slice(&contents).with_context(|| format!("Invalid cache {}", path.display()))
+}
+
+/// Formats `age` with its two most significant units, e.g. `3h 12m`.
+pub(crate) fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86_400,
+        secs % 86_400 / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+    );
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cache-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn store_never_follows_a_planted_link() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir();
+        let victim = dir.join("victim");
+        fs::write(&victim, "untouched").unwrap();
+        std::os::unix::fs::symlink(&victim, dir.join(".cache.json.tmp")).unwrap();
+
+        let path = dir.join("cache.json");
+        store(&path, &CachedConfig::new("{}".to_string(), None)).unwrap();
+
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "untouched");
+        assert_eq!(load(&path).unwrap().body, "{}");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stale_temporaries_are_removed() {
+        let dir = temp_dir();
+        let stale = dir.join(".cache.json.1.abc.tmp");
+        let fresh = dir.join(".cache.json.2.def.tmp");
+        fs::File::create(&stale)
+            .unwrap()
+            .set_modified(SystemTime::now() - 2 * STALE_TEMPORARY_AGE)
+            .unwrap();
+        fs::File::create(&fresh).unwrap();
+
+        store(
+            &dir.join("cache.json"),
+            &CachedConfig::new("{}".to_string(), None),
+        )
+        .unwrap();
+
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, [".cache.json.2.def.tmp", "cache.json"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use crate::database_connection::DatabaseConnectionData;
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const ENV_CONFIG_FILE: &str = "DB_CONFIG_FILE";
+
+/// File set with [`set_config_file`], taking precedence over `DB_CONFIG_FILE`.
+static CONFIG_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Format of a configuration file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detects the format from the extension of `path`: `.toml`, `.json`, `.yaml` or `.yml`.
+    ///
+    /// # Errors
+    /// Returns an error if the extension is missing or unknown.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("yaml" | "yml") => Ok(ConfigFormat::Yaml),
+            _ => Err(anyhow!(
+                "Unknown config file format {}; expected .toml, .json, .yaml or .yml",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Makes [`DatabaseConnectionData::get`] read its base configuration from `path` instead of the
+/// remote endpoint (release) or the empty default (debug). Takes precedence over
+/// `DB_CONFIG_FILE`.
+pub fn set_config_file(path: impl Into<PathBuf>) {
+    *CONFIG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.into());
+}
+
+/// Returns the file set with [`set_config_file`] or `DB_CONFIG_FILE`, if any.
+pub(crate) fn config_file() -> Option<PathBuf> {
+    let explicit = CONFIG_FILE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    explicit.or_else(|| std::env::var_os(ENV_CONFIG_FILE).map(PathBuf::from))
+}
+
+impl DatabaseConnectionData {
+    /// Reads the configuration from a TOML, JSON or YAML file, the format being detected from
+    /// its extension.
+    ///
+    /// `${NAME}` in string values is replaced with the environment variable `NAME`, e.g.
+    /// `password = "${MYSQL_PASSWORD}"`; `$${` renders a literal `${`. Any other `$` is kept as
+    /// is, so values such as `pa$$word` need no escaping. Environment overrides are not applied,
+    /// see [`DatabaseConnectionData::get`].
+    ///
+    /// # Errors
+    /// * The file cannot be read or its format is unknown.
+    /// * The contents are invalid.
+    /// * A referenced environment variable is not set.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Self::parse(&contents, format)
+            .with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Parses a configuration in `format`, with the `${NAME}` interpolation of
+    /// [`from_file`](Self::from_file).
+    ///
+    /// # Errors
+    /// * The contents are invalid.
+    /// * A referenced environment variable is not set.
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<Self> {
+        let mut value: Value = match format {
+            ConfigFormat::Toml => toml::from_str(contents)?,
+            ConfigFormat::Json => serde_json::from_str(contents)?,
+            ConfigFormat::Yaml => serde_norway::from_str(contents)?,
+        };
+        interpolate(&mut value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Expands `${NAME}` in every string of `value`.
+fn interpolate(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) => *s = expand(s)?,
+        Value::Array(items) => items.iter_mut().try_for_each(interpolate)?,
+        Value::Object(fields) => fields.values_mut().try_for_each(interpolate)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replaces `${NAME}` with the environment variable `NAME` and `$${` with `${`.
+fn expand(s: &str) -> Result<String> {
+    let mut output = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let dollar = &rest[start..];
+        if let Some(after) = dollar.strip_prefix("$${") {
+            output.push_str("${");
+            rest = after;
+        } else if let Some(after) = dollar.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                bail!("Unterminated ${{ in a string value");
+            };
+            let name = &after[..end];
+            let value = std::env::var(name)
+                .with_context(|| format!("Environment variable {name:?} is not set"))?;
+            output.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            output.push('$');
+            rest = &dollar[1..];
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_dollar_brace_is_escaped() {
+        assert_eq!(expand("pa$$word").unwrap(), "pa$$word");
+        assert_eq!(expand("cost: $5").unwrap(), "cost: $5");
+        assert_eq!(expand("$${HOME}").unwrap(), "${HOME}");
+        assert_eq!(expand("$$$${X}").unwrap(), "$$${X}");
+        assert!(expand("${UNTERMINATED").is_err());
+    }
+
+    #[test]
+    fn variables_are_expanded_in_yaml() {
+        let path = std::env::var("PATH").unwrap();
+        let yaml = "host: db\nuser: u\npassword: \"${PATH}|p$$w\"\nhash: \"\"\nfilemaker:\n  username: \"\"\n  password: \"\"\n";
+        let config = DatabaseConnectionData::parse(yaml, ConfigFormat::Yaml).unwrap();
+        assert_eq!(config.password, format!("{path}|p$$w"));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::database_connection::{DatabaseConnectionData, create_pool_with_database};
+use anyhow::{Result, anyhow};
+use log::debug;
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, RwLock};
+
+/// Pools registered with [`DatabaseRegistry::register`], by name.
+static POOLS: LazyLock<RwLock<HashMap<String, MySqlPool>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Credentials used by entries that do not carry their own.
+static DEFAULT_CREDENTIALS: Mutex<Option<DatabaseConnectionData>> = Mutex::new(None);
+
+/// Database of a [`DatabaseRegistry`] entry, optionally with its own credentials.
+#[derive(Clone, Debug)]
+pub struct NamedDatabase {
+    database: String,
+    credentials: Option<DatabaseConnectionData>,
+}
+
+impl NamedDatabase {
+    /// Entry connecting to `database` with the registry's default credentials.
+    pub fn new(database: impl Into<String>) -> Self {
+        Self {
+            database: database.into(),
+            credentials: None,
+        }
+    }
+
+    /// Connects with `credentials` instead of the registry's default credentials.
+    pub fn credentials(mut self, credentials: DatabaseConnectionData) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+}
+
+impl From<&str> for NamedDatabase {
+    fn from(database: &str) -> Self {
+        Self::new(database)
+    }
+}
+
+impl From<String> for NamedDatabase {
+    fn from(database: String) -> Self {
+        Self::new(database)
+    }
+}
+
+/// Process-wide registry of named MySQL pools, for services using more than one database.
+///
+/// ```norust
+/// DatabaseRegistry::register("pricing", "pricing").await?;
+/// DatabaseRegistry::register("inventory", NamedDatabase::new("inventory").credentials(other)).await?;
+///
+/// let pool = DatabaseRegistry::get("inventory")?;
+/// ```
+///
+/// Independent of [`set_database_name`](super::set_database_name) and
+/// [`create_pool`](super::create_pool), which keep serving the single-database case. Registered
+/// pools are closed by [`close_pools`](super::close_pools) like any other pool created by this
+/// library.
+pub struct DatabaseRegistry;
+
+impl DatabaseRegistry {
+    /// Sets the credentials of entries registered without their own.
+    ///
+    /// When unset, they are loaded with [`DatabaseConnectionData::get`] on first use.
+    pub fn set_default_credentials(credentials: DatabaseConnectionData) {
+        *DEFAULT_CREDENTIALS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(credentials);
+    }
+
+    /// Connects to `database` and registers the pool as `name`.
+    ///
+    /// # Errors
+    /// * `name` is already registered.
+    /// * Loading the default credentials or connecting fails.
+    pub async fn register(name: &str, database: impl Into<NamedDatabase>) -> Result<MySqlPool> {
+        if Self::contains(name) {
+            return Err(anyhow!("Database {name:?} is already registered"));
+        }
+
+        let database = database.into();
+        let credentials = match database.credentials {
+            Some(credentials) => credentials,
+            None => default_credentials().await?,
+        };
+        debug!("Registering database {name:?} ({})", database.database);
+        let pool = create_pool_with_database(&credentials, &database.database).await?;
+
+        // Another task may have registered the same name while this one was connecting
+        let inserted = {
+            let mut pools = POOLS.write().unwrap_or_else(|e| e.into_inner());
+            !pools.contains_key(name) && pools.insert(name.to_string(), pool.clone()).is_none()
+        };
+        if !inserted {
+            pool.close().await;
+            return Err(anyhow!("Database {name:?} is already registered"));
+        }
+        Ok(pool)
+    }
+
+    /// Returns the pool registered as `name`.
+    ///
+    /// # Errors
+    /// Returns an error if no database is registered as `name`.
+    pub fn get(name: &str) -> Result<MySqlPool> {
+        POOLS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No database registered as {name:?}"))
+    }
+
+    /// Returns `true` if a database is registered as `name`.
+    pub fn contains(name: &str) -> bool {
+        POOLS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(name)
+    }
+
+    /// Returns the registered names.
+    pub fn names() -> Vec<String> {
+        POOLS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Unregisters `name` and closes its pool. Returns `false` if it was not registered.
+    pub async fn remove(name: &str) -> bool {
+        let pool = POOLS
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+        match pool {
+            Some(pool) => {
+                pool.close().await;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Returns the default credentials, loading them on first use.
+async fn default_credentials() -> Result<DatabaseConnectionData> {
+    if let Some(credentials) = DEFAULT_CREDENTIALS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    {
+        return Ok(credentials);
+    }
+    let credentials = DatabaseConnectionData::get().await?;
+    DatabaseRegistry::set_default_credentials(credentials.clone());
+    Ok(credentials)
+}
+
+/// Forgets every registered pool. The pools themselves are closed by the caller.
+pub(crate) fn clear() {
+    POOLS.write().unwrap_or_else(|e| e.into_inner()).clear();
+}
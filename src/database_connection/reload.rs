@@ -0,0 +1,219 @@
+use crate::database_connection::{
+    DatabaseConnectionData, create_pool_with_database, get_database_name,
+};
+use anyhow::Result;
+use log::{info, warn};
+use sqlx::MySqlPool;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, watch};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Default interval between two configuration fetches of [`ReloadablePool::watch`].
+pub const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// MySQL pool rebuilt whenever the connection settings of its [`DatabaseConnectionData`] change,
+/// e.g. after credentials were rotated on the remote config server.
+///
+/// Clones share the same pool. Call [`pool`](Self::pool) for every unit of work instead of
+/// keeping the returned pool, so that work started after a reload uses the new pool:
+///
+/// ```norust
+/// let pool = ReloadablePool::new(DatabaseConnectionData::get().await?).await?;
+/// pool.watch(DEFAULT_RELOAD_INTERVAL);
+///
+/// let rows = sqlx::query("SELECT ...").fetch_all(&pool.pool()).await?;
+/// ```
+///
+/// On a change the new pool is created first and swapped in atomically; the previous pool is then
+/// closed in the background, letting queries already running on it finish.
+#[derive(Clone)]
+pub struct ReloadablePool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    database: String,
+    pool: RwLock<MySqlPool>,
+    config: watch::Sender<DatabaseConnectionData>,
+    /// Held while a reload is in progress so concurrent reloads cannot both swap.
+    reloading: Mutex<()>,
+}
+
+impl ReloadablePool {
+    /// Creates the pool for the database set with [`set_database_name`](super::set_database_name).
+    ///
+    /// # Errors
+    /// * The database name has not been set.
+    /// * Connection to MySQL fails after the configured retries.
+    pub async fn new(config: DatabaseConnectionData) -> Result<Self> {
+        Self::with_database(config, get_database_name()?).await
+    }
+
+    /// Creates the pool for `database`, ignoring the global database name.
+    ///
+    /// # Errors
+    /// Returns an error if connection to MySQL fails after the configured retries.
+    pub async fn with_database(
+        config: DatabaseConnectionData,
+        database: impl Into<String>,
+    ) -> Result<Self> {
+        let database = database.into();
+        let pool = create_pool_with_database(&config, &database).await?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                database,
+                pool: RwLock::new(pool),
+                config: watch::Sender::new(config),
+                reloading: Mutex::new(()),
+            }),
+        })
+    }
+
+    /// Returns the current pool.
+    pub fn pool(&self) -> MySqlPool {
+        self.inner
+            .pool
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Returns the configuration of the current pool.
+    pub fn config(&self) -> DatabaseConnectionData {
+        self.inner.config.borrow().clone()
+    }
+
+    /// Returns a receiver notified with the new configuration after every change, whether or not
+    /// it required a new pool.
+    pub fn subscribe(&self) -> watch::Receiver<DatabaseConnectionData> {
+        self.inner.config.subscribe()
+    }
+
+    /// Swaps in a pool built from `config` if its connection settings (host, user, password,
+    /// port, TLS or pool settings) differ from the current ones.
+    ///
+    /// Other changes, e.g. to the FileMaker credentials or the hash, only update
+    /// [`config`](Self::config) and notify [subscribers](Self::subscribe), keeping the pool.
+    /// Returns `true` if the pool was replaced. The previous pool is closed in the background.
+    ///
+    /// # Errors
+    /// Returns an error if the new pool cannot connect, in which case the current pool is kept.
+    pub async fn reload(&self, config: DatabaseConnectionData) -> Result<bool> {
+        let _reloading = self.inner.reloading.lock().await;
+        let database = &self.inner.database;
+        {
+            let current = self.inner.config.borrow();
+            if *current == config {
+                return Ok(false);
+            }
+            if !connection_changed(&current, &config) {
+                drop(current);
+                info!("Database configuration of {database} changed; keeping the current pool");
+                self.inner.config.send_replace(config);
+                return Ok(false);
+            }
+        }
+
+        info!("Database configuration of {database} changed; creating a new pool");
+        let pool = create_pool_with_database(&config, database).await?;
+        let previous = std::mem::replace(
+            &mut *self.inner.pool.write().unwrap_or_else(|e| e.into_inner()),
+            pool,
+        );
+        self.inner.config.send_replace(config);
+        info!("Swapped the {database} pool; draining the previous one");
+
+        let database = database.clone();
+        tokio::spawn(async move {
+            previous.close().await;
+            info!("Previous {database} pool drained");
+        });
+        Ok(true)
+    }
+
+    /// Re-fetches the configuration with [`DatabaseConnectionData::get`] every `interval` and
+    /// [reloads](Self::reload) the pool when it changed.
+    ///
+    /// See [`watch_with`](Self::watch_with).
+    pub fn watch(&self, interval: Duration) -> JoinHandle<()> {
+        self.watch_with(interval, DatabaseConnectionData::get)
+    }
+
+    /// Calls `load` every `interval` and [reloads](Self::reload) the pool with the configuration
+    /// it returns.
+    ///
+    /// Failures are logged and the current pool is kept. The task stops once every handle of
+    /// this pool has been dropped or the pool was closed, e.g. by
+    /// [`close_pools`](super::close_pools); it can also be stopped by aborting the returned
+    /// handle.
+    pub fn watch_with<F, Fut>(&self, interval: Duration, load: F) -> JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<DatabaseConnectionData>> + Send + 'static,
+    {
+        let inner = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // The first tick completes immediately and the configuration was just loaded
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(inner) = inner.upgrade() else {
+                    break;
+                };
+                let pool = ReloadablePool { inner };
+                if pool.pool().is_closed() {
+                    break;
+                }
+                match load().await {
+                    Ok(config) => {
+                        if let Err(e) = pool.reload(config).await {
+                            warn!(
+                                "Failed to apply the new configuration of {}, keeping the current pool: {e:#}",
+                                pool.inner.database
+                            );
+                        }
+                    }
+                    Err(e) => warn!("Failed to reload the database configuration: {e:#}"),
+                }
+            }
+        })
+    }
+}
+
+/// Whether a pool created from `current` would connect differently with `new`, i.e. whether any
+/// setting read by [`create_pool_with_database`] differs.
+fn connection_changed(current: &DatabaseConnectionData, new: &DatabaseConnectionData) -> bool {
+    current.host != new.host
+        || current.user != new.user
+        || current.password != new.password
+        || current.port != new.port
+        || current.ssl != new.ssl
+        || current.pool != new.pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_connection_settings_require_a_new_pool() {
+        let current = DatabaseConnectionData::default();
+
+        let mut new = current.clone();
+        new.hash = "rotated".to_string();
+        new.filemaker.password = "rotated".to_string();
+        assert!(!connection_changed(&current, &new));
+
+        new.password = "rotated".to_string();
+        assert!(connection_changed(&current, &new));
+
+        let mut new = current.clone();
+        new.pool.max_connections = Some(20);
+        assert!(connection_changed(&current, &new));
+    }
+}
@@ -0,0 +1,893 @@
+use crate::database_connection::cache::{self, CachedConfig};
+use crate::database_connection::{DatabaseConnectionData, parse_env};
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use log::warn;
+use reqwest::Certificate;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use ring::digest::{SHA256, digest};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, SignatureScheme};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Default URL of the remote production configuration.
+pub const DEFAULT_REMOTE_CONFIG_URL: &str = "https://lib.mardens.com/config.json";
+
+/// Default time allowed to establish the connection to the remote config endpoint.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time allowed for the whole remote config request, including the body.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default age after which a cached configuration is no longer used, see
+/// [`RemoteConfigOptions::cache_max_staleness`].
+pub const DEFAULT_CACHE_MAX_STALENESS: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Response header carrying the base64 Ed25519 signature of the remote config body.
+pub const SIGNATURE_HEADER: &str = "X-Config-Signature";
+
+const ENV_CONFIG_URL: &str = "DB_CONFIG_URL";
+const ENV_CONFIG_APP: &str = "DB_CONFIG_APP";
+const ENV_CONFIG_ENV: &str = "DB_CONFIG_ENV";
+const ENV_CONFIG_TOKEN: &str = "DB_CONFIG_TOKEN";
+const ENV_CONFIG_USERNAME: &str = "DB_CONFIG_USERNAME";
+const ENV_CONFIG_PASSWORD: &str = "DB_CONFIG_PASSWORD";
+const ENV_CONFIG_HEADER: &str = "DB_CONFIG_HEADER";
+const ENV_CONFIG_CONNECT_TIMEOUT: &str = "DB_CONFIG_CONNECT_TIMEOUT";
+const ENV_CONFIG_TIMEOUT: &str = "DB_CONFIG_TIMEOUT";
+const ENV_CONFIG_CACHE: &str = "DB_CONFIG_CACHE";
+const ENV_CONFIG_CACHE_MAX_STALENESS: &str = "DB_CONFIG_CACHE_MAX_STALENESS";
+const ENV_CONFIG_CA: &str = "DB_CONFIG_CA";
+const ENV_CONFIG_PIN_SHA256: &str = "DB_CONFIG_PIN_SHA256";
+const ENV_CONFIG_ACCEPT_INVALID_CERTS: &str = "DB_CONFIG_DANGER_ACCEPT_INVALID_CERTS";
+const ENV_CONFIG_PUBLIC_KEY: &str = "DB_CONFIG_PUBLIC_KEY";
+
+/// Verification key compiled in when `DB_CONFIG_PUBLIC_KEY` is set at build time.
+const BUILTIN_PUBLIC_KEY: Option<&str> = option_env!("DB_CONFIG_PUBLIC_KEY");
+
+/// Values of `DB_CONFIG_CACHE` disabling the cache.
+const CACHE_DISABLED_VALUES: [&str; 2] = ["", "off"];
+
+/// Options set with [`set_remote_config_options`]. When unset they are read from the environment.
+static OPTIONS: RwLock<Option<RemoteConfigOptions>> = RwLock::new(None);
+
+/// Where the remote configuration is fetched from and how the endpoint is authenticated.
+///
+/// By default the configuration is fetched from [`DEFAULT_REMOTE_CONFIG_URL`], the server
+/// certificate is validated against the system roots and, when a public key was compiled in, the
+/// body must carry a valid [`SIGNATURE_HEADER`]. Unless replaced with
+/// [`set_remote_config_options`], the options come from [`RemoteConfigOptions::from_env`].
+///
+/// ```norust
+/// set_remote_config_options(
+///     RemoteConfigOptions::new()
+///         .url("https://config.example.com/config/{app}/{env}.json")
+///         .app("pricing")
+///         .environment("staging")
+///         .bearer_token(&token)?,
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct RemoteConfigOptions {
+    url: String,
+    app: Option<String>,
+    environment: Option<String>,
+    headers: HeaderMap,
+    connect_timeout: Duration,
+    timeout: Duration,
+    cache_file: CacheFile,
+    cache_max_staleness: Option<Duration>,
+    ca_certificates: Vec<Certificate>,
+    pins: Vec<[u8; 32]>,
+    accept_invalid_certs: bool,
+    public_key: Option<String>,
+}
+
+/// Where the last fetched configuration is kept, see [`RemoteConfigOptions::cache_file`].
+#[derive(Clone, Debug)]
+enum CacheFile {
+    /// A file per configuration URL in the user's cache directory.
+    Default,
+    Path(PathBuf),
+    Disabled,
+}
+
+impl Default for RemoteConfigOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RemoteConfigOptions {
+    /// Fetches [`DEFAULT_REMOTE_CONFIG_URL`] without authentication, validating certificates
+    /// against the system roots and signatures against the compiled-in public key, if any.
+    pub fn new() -> Self {
+        Self {
+            url: DEFAULT_REMOTE_CONFIG_URL.to_string(),
+            app: None,
+            environment: None,
+            headers: HeaderMap::new(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            cache_file: CacheFile::Default,
+            cache_max_staleness: Some(DEFAULT_CACHE_MAX_STALENESS),
+            ca_certificates: Vec::new(),
+            pins: Vec::new(),
+            accept_invalid_certs: false,
+            public_key: BUILTIN_PUBLIC_KEY.map(str::to_string),
+        }
+    }
+
+    /// Reads the options from the environment:
+    ///
+    /// * `DB_CONFIG_URL` — URL of the configuration, see [`url`](Self::url).
+    /// * `DB_CONFIG_APP` and `DB_CONFIG_ENV` — values of the `{app}` and `{env}` placeholders.
+    /// * `DB_CONFIG_TOKEN` — bearer token.
+    /// * `DB_CONFIG_USERNAME` and `DB_CONFIG_PASSWORD` — basic authentication credentials.
+    /// * `DB_CONFIG_HEADER` — custom header as `Name: value`.
+    /// * `DB_CONFIG_CONNECT_TIMEOUT` and `DB_CONFIG_TIMEOUT` — timeouts in seconds.
+    /// * `DB_CONFIG_CACHE` — cache file, see [`cache_file`](Self::cache_file); empty or `off`
+    ///   disables the cache.
+    /// * `DB_CONFIG_CACHE_MAX_STALENESS` — maximum age of the cache in seconds, `0` for no limit.
+    /// * `DB_CONFIG_CA` — PEM bundle trusted in addition to the system roots.
+    /// * `DB_CONFIG_PIN_SHA256` — comma-separated SHA-256 fingerprints of accepted certificates.
+    /// * `DB_CONFIG_DANGER_ACCEPT_INVALID_CERTS` — `true` to skip certificate validation.
+    /// * `DB_CONFIG_PUBLIC_KEY` — base64 Ed25519 public key, replacing the compiled-in one.
+    ///
+    /// # Errors
+    /// Returns an error if a variable is set but invalid.
+    pub fn from_env() -> Result<Self> {
+        let mut options = Self::new();
+        if let Ok(url) = std::env::var(ENV_CONFIG_URL) {
+            options = options.url(url);
+        }
+        if let Ok(app) = std::env::var(ENV_CONFIG_APP) {
+            options = options.app(app);
+        }
+        if let Ok(environment) = std::env::var(ENV_CONFIG_ENV) {
+            options = options.environment(environment);
+        }
+        if let Ok(token) = std::env::var(ENV_CONFIG_TOKEN) {
+            options = options
+                .bearer_token(&token)
+                .with_context(|| format!("Invalid {ENV_CONFIG_TOKEN}"))?;
+        }
+        if let Ok(username) = std::env::var(ENV_CONFIG_USERNAME) {
+            let password = std::env::var(ENV_CONFIG_PASSWORD).ok();
+            options = options
+                .basic_auth(&username, password.as_deref())
+                .with_context(|| {
+                    format!("Invalid {ENV_CONFIG_USERNAME} or {ENV_CONFIG_PASSWORD}")
+                })?;
+        }
+        if let Ok(header) = std::env::var(ENV_CONFIG_HEADER) {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("{ENV_CONFIG_HEADER} must be formatted as `Name: value`"))?;
+            options = options
+                .header(name.trim(), value.trim())
+                .with_context(|| format!("Invalid {ENV_CONFIG_HEADER}"))?;
+        }
+        if let Some(secs) = parse_env(ENV_CONFIG_CONNECT_TIMEOUT, "number of seconds")? {
+            options = options.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = parse_env(ENV_CONFIG_TIMEOUT, "number of seconds")? {
+            options = options.timeout(Duration::from_secs(secs));
+        }
+        if let Ok(path) = std::env::var(ENV_CONFIG_CACHE) {
+            options = if CACHE_DISABLED_VALUES.contains(&path.trim()) {
+                options.disable_cache()
+            } else {
+                options.cache_file(path)
+            };
+        }
+        if let Some(secs) = parse_env(ENV_CONFIG_CACHE_MAX_STALENESS, "number of seconds")? {
+            options = options.cache_max_staleness((secs > 0).then(|| Duration::from_secs(secs)));
+        }
+        if let Ok(path) = std::env::var(ENV_CONFIG_CA) {
+            options = options.ca_file(path)?;
+        }
+        if let Ok(pins) = std::env::var(ENV_CONFIG_PIN_SHA256) {
+            for pin in pins.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                options = options
+                    .pin_sha256(pin)
+                    .with_context(|| format!("Invalid {ENV_CONFIG_PIN_SHA256}"))?;
+            }
+        }
+        if let Some(accept) = parse_env(ENV_CONFIG_ACCEPT_INVALID_CERTS, "boolean")? {
+            options = options.danger_accept_invalid_certs(accept);
+        }
+        if let Ok(key) = std::env::var(ENV_CONFIG_PUBLIC_KEY) {
+            options = options
+                .public_key(&key)
+                .with_context(|| format!("Invalid {ENV_CONFIG_PUBLIC_KEY}"))?;
+        }
+        Ok(options)
+    }
+
+    /// Fetches the configuration from `url`.
+    ///
+    /// `{app}` and `{env}` in `url` are replaced with the values of [`app`](Self::app) and
+    /// [`environment`](Self::environment), e.g. `https://config.example.com/config/{app}/{env}.json`
+    /// for per-application and per-environment configurations.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Sets the value of the `{app}` placeholder of the URL.
+    pub fn app(mut self, app: impl Into<String>) -> Self {
+        self.app = Some(app.into());
+        self
+    }
+
+    /// Sets the value of the `{env}` placeholder of the URL, e.g. `production` or `staging`.
+    pub fn environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Authenticates with `Authorization: Bearer <token>`.
+    ///
+    /// # Errors
+    /// Returns an error if `token` is not a valid header value.
+    pub fn bearer_token(self, token: &str) -> Result<Self> {
+        self.header(AUTHORIZATION, &format!("Bearer {token}"))
+    }
+
+    /// Authenticates with HTTP basic authentication.
+    ///
+    /// # Errors
+    /// Returns an error if the credentials are not a valid header value.
+    pub fn basic_auth(self, username: &str, password: Option<&str>) -> Result<Self> {
+        let credentials = BASE64.encode(format!("{username}:{}", password.unwrap_or_default()));
+        self.header(AUTHORIZATION, &format!("Basic {credentials}"))
+    }
+
+    /// Sends the header `name: value` with every request, e.g. an API key. The value is never
+    /// logged.
+    ///
+    /// # Errors
+    /// Returns an error if `name` or `value` is not a valid header.
+    pub fn header<N>(mut self, name: N, value: &str) -> Result<Self>
+    where
+        N: TryInto<HeaderName>,
+        N::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let name = name.try_into()?;
+        let mut value = HeaderValue::from_str(value)?;
+        value.set_sensitive(true);
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Sets the time allowed to establish the connection. Defaults to
+    /// [`DEFAULT_CONNECT_TIMEOUT`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the time allowed for the whole request, including the body. Defaults to
+    /// [`DEFAULT_TIMEOUT`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Keeps the last successfully fetched configuration in `path`, used when a later fetch fails.
+    ///
+    /// The file holds the database credentials and is only accessible to the current user on
+    /// Unix (`0600`). The signature is stored along with the body and checked again when the
+    /// cache is used. Defaults to `remote-config-<hash of the URL>.json` in
+    /// `$XDG_CACHE_HOME/database-common-lib` or `~/.cache/database-common-lib`, so services
+    /// fetching different configurations never share a cache.
+    pub fn cache_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_file = CacheFile::Path(path.into());
+        self
+    }
+
+    /// Never caches the configuration, so a failed fetch always fails.
+    pub fn disable_cache(mut self) -> Self {
+        self.cache_file = CacheFile::Disabled;
+        self
+    }
+
+    /// Sets the age after which the cache is no longer used and a failed fetch fails the startup,
+    /// or `None` to use it regardless of its age. Defaults to [`DEFAULT_CACHE_MAX_STALENESS`].
+    pub fn cache_max_staleness(mut self, max_staleness: Option<Duration>) -> Self {
+        self.cache_max_staleness = max_staleness;
+        self
+    }
+
+    /// Trusts the certificates of a PEM bundle in addition to the system roots.
+    ///
+    /// # Errors
+    /// Returns an error if `pem` contains no valid certificate.
+    pub fn ca_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certificates = Certificate::from_pem_bundle(pem)?;
+        if certificates.is_empty() {
+            bail!("CA bundle contains no certificate");
+        }
+        self.ca_certificates.extend(certificates);
+        Ok(self)
+    }
+
+    /// Trusts the certificates of a PEM bundle file in addition to the system roots.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or contains no valid certificate.
+    pub fn ca_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let pem = std::fs::read(path)
+            .with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+        self.ca_pem(&pem)
+            .with_context(|| format!("Invalid CA bundle {}", path.display()))
+    }
+
+    /// Only accepts a server certificate whose SHA-256 fingerprint is `fingerprint`, given in hex
+    /// with or without `:` separators. Can be called several times to accept any of the pins.
+    ///
+    /// The pin is checked during the TLS handshake, before any request header is sent. A pinned
+    /// certificate is trusted on its own, so self-signed certificates work without a CA bundle.
+    ///
+    /// # Errors
+    /// Returns an error if `fingerprint` is not 32 hex-encoded bytes.
+    pub fn pin_sha256(mut self, fingerprint: &str) -> Result<Self> {
+        let hex: Vec<u8> = fingerprint.bytes().filter(|b| *b != b':').collect();
+        let mut pin = [0u8; 32];
+        if hex.len() != pin.len() * 2 {
+            bail!("SHA-256 fingerprint must be 64 hex digits (got {fingerprint:?})");
+        }
+        for (byte, pair) in pin.iter_mut().zip(hex.chunks(2)) {
+            *byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid SHA-256 fingerprint {fingerprint:?}"))?;
+        }
+        self.pins.push(pin);
+        Ok(self)
+    }
+
+    /// Disables certificate validation, for legacy hosts that cannot serve a valid certificate.
+    ///
+    /// Anyone on the network path can then serve the configuration; prefer
+    /// [`pin_sha256`](Self::pin_sha256) and a [`public_key`](Self::public_key).
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Requires the body to be signed with the Ed25519 key whose base64 public key is `key`.
+    ///
+    /// The signature only covers the body, so when the URL contains `{app}` or `{env}` the signed
+    /// body must also name them in top-level `"app"` and `"env"` members; otherwise a config
+    /// signed for one application or environment could be served for another.
+    ///
+    /// # Errors
+    /// Returns an error if `key` is not a base64-encoded 32-byte key.
+    pub fn public_key(mut self, key: &str) -> Result<Self> {
+        decode_public_key(key)?;
+        self.public_key = Some(key.to_string());
+        Ok(self)
+    }
+
+    /// Builds the HTTP client used to talk to the remote configuration endpoint.
+    fn client(&self) -> Result<reqwest::Client> {
+        // Redirects are not followed so the authentication headers never reach another host
+        let mut builder = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .default_headers(self.headers.clone())
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout);
+        for certificate in &self.ca_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if !self.pins.is_empty() {
+            // The pin replaces chain and hostname validation
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let config = ClientConfig::builder_with_provider(provider.clone())
+                .with_safe_default_protocol_versions()?
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                    pins: self.pins.clone(),
+                    provider,
+                }))
+                .with_no_client_auth();
+            builder = builder.use_preconfigured_tls(config);
+        } else if self.accept_invalid_certs {
+            warn!("Certificate validation of the remote config endpoint is disabled");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Returns the URL with its `{app}` and `{env}` placeholders replaced.
+    /// Path of the cache file, if caching is enabled and a cache directory is known.
+    fn cache_path(&self) -> Option<PathBuf> {
+        match &self.cache_file {
+            CacheFile::Path(path) => Some(path.clone()),
+            CacheFile::Disabled => None,
+            CacheFile::Default => {
+                let url = self.resolved_url().ok()?;
+                let hash = digest(&SHA256, url.as_bytes());
+                let hash: String = hash.as_ref()[..8]
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect();
+                Some(default_cache_dir()?.join(format!("remote-config-{hash}.json")))
+            }
+        }
+    }
+
+    fn resolved_url(&self) -> Result<String> {
+        let mut url = self.url.clone();
+        for (placeholder, value, env) in [
+            ("{app}", &self.app, ENV_CONFIG_APP),
+            ("{env}", &self.environment, ENV_CONFIG_ENV),
+        ] {
+            if !url.contains(placeholder) {
+                continue;
+            }
+            let value = value.as_deref().ok_or_else(|| {
+                anyhow!("Remote config URL contains {placeholder} but {env} is not set")
+            })?;
+            // Values become path segments, so they must not be able to escape them
+            if value.is_empty()
+                || !value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                || value.chars().all(|c| c == '.')
+            {
+                bail!("Invalid value {value:?} for {placeholder} in the remote config URL");
+            }
+            url = url.replace(placeholder, value);
+        }
+        Ok(url)
+    }
+
+    /// Checks `signature`, the [`SIGNATURE_HEADER`] of `body`, when a public key is configured.
+    fn verify_signature(&self, signature: Option<&str>, body: &[u8]) -> Result<()> {
+        let Some(key) = &self.public_key else {
+            return Ok(());
+        };
+        let key = decode_public_key(key)?;
+        let signature = signature
+            .ok_or_else(|| anyhow!("Remote config response has no {SIGNATURE_HEADER} header"))?;
+        let signature = BASE64
+            .decode(signature.trim())
+            .with_context(|| format!("Invalid {SIGNATURE_HEADER} header"))?;
+        UnparsedPublicKey::new(&ED25519, key)
+            .verify(body, &signature)
+            .map_err(|_| anyhow!("Remote config signature verification failed"))
+    }
+
+    /// Checks that a signed `body` names the `{app}` and `{env}` values of the URL, so a signature
+    /// cannot be replayed for another application or environment.
+    fn verify_scope(&self, body: &str) -> Result<()> {
+        if self.public_key.is_none() {
+            return Ok(());
+        }
+        let value: serde_json::Value = serde_json::from_str(body)?;
+        for (placeholder, member, expected) in [
+            ("{app}", "app", &self.app),
+            ("{env}", "env", &self.environment),
+        ] {
+            if !self.url.contains(placeholder) {
+                continue;
+            }
+            let actual = value.get(member).and_then(|v| v.as_str());
+            if actual != expected.as_deref() {
+                bail!(
+                    "Signed remote config is for {member} {actual:?}, expected {:?}",
+                    expected.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches and verifies the configuration. Returns it with its cache entry.
+    async fn fetch(&self) -> Result<(DatabaseConnectionData, CachedConfig)> {
+        let response = self
+            .client()?
+            .get(self.resolved_url()?)
+            .send()
+            .await?
+            .error_for_status()?;
+        let signature = response
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .map(|value| value.to_str().map(str::to_string))
+            .transpose()
+            .with_context(|| format!("Invalid {SIGNATURE_HEADER} header"))?;
+        let body = String::from_utf8(response.bytes().await?.to_vec())
+            .context("Remote config is not valid UTF-8")?;
+        self.verify_signature(signature.as_deref(), body.as_bytes())?;
+        self.verify_scope(&body)?;
+        let config = serde_json::from_str(&body)?;
+        Ok((config, CachedConfig::new(body, signature)))
+    }
+
+    /// Loads the cached configuration if it is recent enough. Returns it with its age.
+    fn load_cached(&self, path: &Path) -> Result<(DatabaseConnectionData, Duration)> {
+        let cached = cache::load(path)?;
+        let age = cached.age();
+        if let Some(max_staleness) = self.cache_max_staleness
+            && age > max_staleness
+        {
+            bail!(
+                "Cached config in {} is {} old, more than the allowed {}",
+                path.display(),
+                cache::format_age(age),
+                cache::format_age(max_staleness)
+            );
+        }
+        self.verify_signature(cached.signature.as_deref(), cached.body.as_bytes())?;
+        self.verify_scope(&cached.body)?;
+        Ok((serde_json::from_str(&cached.body)?, age))
+    }
+}
+
+/// Accepts only server certificates whose SHA-256 fingerprint is pinned, failing the handshake
+/// otherwise.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pins: Vec<[u8; 32]>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = digest(&SHA256, end_entity.as_ref());
+        if self
+            .pins
+            .iter()
+            .any(|pin| pin.as_slice() == fingerprint.as_ref())
+        {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            warn!("Remote config certificate does not match any pinned fingerprint");
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// Replaces the options used to fetch the remote configuration, ignoring the `DB_CONFIG_*`
+/// variables.
+pub fn set_remote_config_options(options: RemoteConfigOptions) {
+    *OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = Some(options);
+}
+
+/// Returns the options set with [`set_remote_config_options`] or read from the environment.
+fn options() -> Result<RemoteConfigOptions> {
+    match OPTIONS.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(options) => Ok(options.clone()),
+        None => RemoteConfigOptions::from_env(),
+    }
+}
+
+fn decode_public_key(key: &str) -> Result<Vec<u8>> {
+    let key = BASE64
+        .decode(key.trim())
+        .context("Public key is not valid base64")?;
+    if key.len() != 32 {
+        bail!("Ed25519 public key must be 32 bytes (got {})", key.len());
+    }
+    Ok(key)
+}
+
+/// Per-user cache directory of this crate: `$XDG_CACHE_HOME`, then `~/.cache`, then
+/// `%LOCALAPPDATA%`.
+fn default_cache_dir() -> Option<PathBuf> {
+    let from_env = |name: &str, suffix: &str| {
+        std::env::var_os(name)
+            .map(|dir| PathBuf::from(dir).join(suffix))
+            .filter(|dir| dir.is_absolute())
+    };
+    let base = from_env("XDG_CACHE_HOME", "")
+        .or_else(|| from_env("HOME", ".cache"))
+        .or_else(|| from_env("LOCALAPPDATA", ""))?;
+    Some(base.join(env!("CARGO_PKG_NAME")))
+}
+
+/// Fetches the remote production configuration JSON.
+///
+/// The body is only parsed once the certificate pins and the signature have been checked. When a
+/// [cache file](RemoteConfigOptions::cache_file) is enabled, a successful fetch replaces it and
+/// a failed one falls back to it with a warning.
+pub(crate) async fn fetch_remote_config() -> Result<DatabaseConnectionData> {
+    let options = options()?;
+    let cache_path = options.cache_path();
+    let error = match options.fetch().await {
+        Ok((config, cached)) => {
+            if let Some(path) = &cache_path
+                && let Err(e) = cache::store(path, &cached)
+            {
+                warn!(
+                    "Failed to cache the remote config in {}: {e:#}",
+                    path.display()
+                );
+            }
+            return Ok(config);
+        }
+        Err(e) => e,
+    };
+
+    let Some(path) = &cache_path else {
+        return Err(error);
+    };
+    match options.load_cached(path) {
+        Ok((config, age)) => {
+            warn!(
+                "Failed to fetch the remote config, using the copy cached in {} {} ago: {error:#}",
+                path.display(),
+                cache::format_age(age)
+            );
+            Ok(config)
+        }
+        Err(cache_error) => Err(error.context(format!(
+            "Failed to fetch the remote config and no usable cached copy exists ({cache_error:#})"
+        ))),
+    }
+}
+
+/// Checks that the remote configuration endpoint is reachable and answers with a success
+/// status. The body is not parsed.
+///
+/// # Errors
+/// * The options are invalid, e.g. a URL placeholder has no value.
+/// * The request fails or times out.
+/// * The certificate does not match the pinned fingerprints.
+/// * The endpoint responds with a non-2xx status.
+pub async fn ping_remote_config() -> Result<()> {
+    let options = options()?;
+    options
+        .client()?
+        .get(options.resolved_url()?)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+    use rustls::ServerConfig;
+    use rustls::pki_types::PrivateKeyDer;
+    use std::sync::Mutex;
+
+    const CONFIG: &str = r#"{"host":"db","user":"u","password":"p","hash":"","filemaker":{"username":"","password":""}}"#;
+
+    /// Serves [`CONFIG`] over TLS with a fresh self-signed certificate. Returns the URL, the
+    /// certificate fingerprint and the `Authorization` headers received.
+    fn serve() -> (String, String, Arc<Mutex<Vec<String>>>) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let fingerprint = digest(&SHA256, certified.cert.der())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(":");
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(
+                    vec![certified.cert.der().clone()],
+                    PrivateKeyDer::Pkcs8(certified.signing_key.serialize_der().into()),
+                )
+                .unwrap();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        let server = HttpServer::new(move || {
+            let seen = seen.clone();
+            App::new().default_service(web::to(move |req: HttpRequest| {
+                let authorization = req
+                    .headers()
+                    .get("authorization")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                seen.lock().unwrap().push(authorization);
+                async { HttpResponse::Ok().body(CONFIG) }
+            }))
+        })
+        .workers(1)
+        .bind_rustls_0_23(("127.0.0.1", 0), config)
+        .unwrap();
+        let port = server.addrs()[0].port();
+        actix_web::rt::spawn(server.run());
+        (
+            format!("https://localhost:{port}/config.json"),
+            fingerprint,
+            received,
+        )
+    }
+
+    #[test]
+    fn cache_defaults_to_a_file_per_configuration() {
+        let options = |app: &str| {
+            RemoteConfigOptions::new()
+                .url("https://config.example.com/{app}.json")
+                .app(app)
+        };
+        if let Some(dir) = default_cache_dir() {
+            let pricing = options("pricing").cache_path().unwrap();
+            let billing = options("billing").cache_path().unwrap();
+            assert_eq!(pricing.parent(), Some(dir.as_path()));
+            assert_ne!(pricing, billing);
+            assert_eq!(options("pricing").cache_path(), Some(pricing));
+        }
+        assert_eq!(options("pricing").disable_cache().cache_path(), None);
+        assert_eq!(
+            options("pricing").cache_file("/tmp/db.json").cache_path(),
+            Some(PathBuf::from("/tmp/db.json"))
+        );
+    }
+
+    #[actix_web::test]
+    async fn pinned_certificate_is_accepted() {
+        let (url, fingerprint, received) = serve();
+        let options = RemoteConfigOptions::new()
+            .url(url)
+            .pin_sha256(&fingerprint)
+            .unwrap()
+            .bearer_token("secret")
+            .unwrap();
+
+        let (config, _) = options.fetch().await.unwrap();
+        assert_eq!(config.host, "db");
+        assert_eq!(*received.lock().unwrap(), ["Bearer secret"]);
+    }
+
+    #[actix_web::test]
+    async fn pin_mismatch_fails_before_the_request_is_sent() {
+        let (url, _, received) = serve();
+        let options = RemoteConfigOptions::new()
+            .url(url)
+            .pin_sha256(&"00".repeat(32))
+            .unwrap()
+            .bearer_token("secret")
+            .unwrap();
+
+        assert!(options.fetch().await.is_err());
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn self_signed_certificate_is_rejected_without_pin() {
+        let (url, _, received) = serve();
+        let options = RemoteConfigOptions::new().url(url);
+
+        assert!(options.fetch().await.is_err());
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    /// Serves `body` over plain HTTP with `headers`. Returns the base URL and the value of the
+    /// `X-Api-Key` header of every request received.
+    fn serve_http(
+        status: u16,
+        headers: Vec<(&'static str, String)>,
+        body: String,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        let server = HttpServer::new(move || {
+            let (seen, headers, body) = (seen.clone(), headers.clone(), body.clone());
+            App::new().default_service(web::to(move |req: HttpRequest| {
+                let key = req
+                    .headers()
+                    .get("x-api-key")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                seen.lock().unwrap().push(key);
+                let mut response =
+                    HttpResponse::build(actix_web::http::StatusCode::from_u16(status).unwrap());
+                for (name, value) in &headers {
+                    response.insert_header((*name, value.as_str()));
+                }
+                let body = body.clone();
+                async move { response.body(body) }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let port = server.addrs()[0].port();
+        actix_web::rt::spawn(server.run());
+        (format!("http://127.0.0.1:{port}"), received)
+    }
+
+    #[actix_web::test]
+    async fn redirects_are_not_followed() {
+        let (target, leaked) = serve_http(200, Vec::new(), CONFIG.to_string());
+        let (origin, _) = serve_http(
+            302,
+            vec![("location", format!("{target}/config.json"))],
+            String::new(),
+        );
+        let options = RemoteConfigOptions::new()
+            .url(format!("{origin}/config.json"))
+            .header("X-Api-Key", "secret")
+            .unwrap();
+
+        assert!(options.fetch().await.is_err());
+        assert!(leaked.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn signed_config_is_bound_to_its_app() {
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let body = CONFIG.replacen('{', r#"{"app":"pricing","#, 1);
+        let signature = BASE64.encode(key.sign(body.as_bytes()));
+        let (base, _) = serve_http(200, vec![(SIGNATURE_HEADER, signature)], body);
+        let options = |app: &str| {
+            RemoteConfigOptions::new()
+                .url(format!("{base}/config/{{app}}.json"))
+                .app(app)
+                .public_key(&BASE64.encode(key.public_key()))
+                .unwrap()
+        };
+
+        assert_eq!(options("pricing").fetch().await.unwrap().0.host, "db");
+        assert!(options("inventory").fetch().await.is_err());
+    }
+}
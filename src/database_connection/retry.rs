@@ -0,0 +1,173 @@
+use anyhow::{Result, anyhow};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How [`create_pool`](super::create_pool) retries the initial connection, e.g. while MySQL is
+/// still starting.
+///
+/// The delay before attempt `n + 1` is `initial_delay_ms * multiplier^(n - 1)`, capped at
+/// `max_delay_ms` and randomized by ±`jitter`. The default makes a single attempt.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total number of connection attempts, including the first.
+    pub max_attempts: u32,
+    /// Delay before the first retry, in milliseconds.
+    pub initial_delay_ms: u64,
+    /// Factor applied to the delay after every failed retry.
+    pub multiplier: f64,
+    /// Upper bound of the delay, in milliseconds.
+    pub max_delay_ms: u64,
+    /// Random spread of each delay as a fraction, e.g. `0.2` for ±20%.
+    pub jitter: f64,
+    /// Seconds after which no further attempt is made, regardless of `max_attempts`.
+    pub deadline_secs: Option<u64>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_delay_ms: 500,
+            multiplier: 2.0,
+            max_delay_ms: 30_000,
+            jitter: 0.2,
+            deadline_secs: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy retrying up to `max_attempts` times with the default backoff.
+    pub fn attempts(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the retry following failed attempt `attempt` (1-based).
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let base = self.initial_delay_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * random_unit() - 1.0);
+        let millis = (base.min(self.max_delay_ms as f64) * factor).max(0.0);
+        Duration::from_millis(millis as u64)
+    }
+
+    /// Delay before the attempt following failed attempt `attempt`, or `None` once the attempts
+    /// are exhausted or the retry would start after `deadline`.
+    fn next_delay(&self, attempt: u32, deadline: Option<Instant>) -> Option<Duration> {
+        let delay = self.delay(attempt);
+        let out_of_time = deadline.is_some_and(|deadline| Instant::now() + delay >= deadline);
+        (attempt < self.max_attempts.max(1) && !out_of_time).then_some(delay)
+    }
+}
+
+/// Connects `pool_options` to `connect_options`, retrying according to `policy`.
+///
+/// # Errors
+/// Returns the last connection error once the attempts or the deadline are exhausted.
+pub(crate) async fn connect_with_retry(
+    pool_options: &MySqlPoolOptions,
+    connect_options: &MySqlConnectOptions,
+    policy: &RetryPolicy,
+) -> Result<MySqlPool> {
+    let max_attempts = policy.max_attempts.max(1);
+    let deadline = policy
+        .deadline_secs
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    let mut attempt = 1;
+    loop {
+        let connect = pool_options.clone().connect_with(connect_options.clone());
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, connect)
+                .await
+                .unwrap_or_else(|_| Err(sqlx::Error::PoolTimedOut)),
+            None => connect.await,
+        };
+        let error = match result {
+            Ok(pool) => return Ok(pool),
+            Err(e) => e,
+        };
+
+        let Some(delay) = policy.next_delay(attempt, deadline) else {
+            return Err(anyhow!(error).context(format!(
+                "Failed to connect to MySQL after {attempt} attempt(s)"
+            )));
+        };
+        warn!(
+            "MySQL connection attempt {attempt}/{max_attempts} failed: {error}; retrying in {delay:?}"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Returns a random number in `[0, 1)`.
+fn random_unit() -> f64 {
+    // The low half of a v4 UUID is random apart from its two top (variant) bits
+    let (_, random) = uuid::Uuid::new_v4().as_u64_pair();
+    (random & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            initial_delay_ms: 500,
+            multiplier: 2.0,
+            max_delay_ms: 3_000,
+            jitter: 0.0,
+            deadline_secs: None,
+        }
+    }
+
+    #[test]
+    fn delays_grow_up_to_the_cap() {
+        let delays = (1..=6)
+            .map(|attempt| policy().delay(attempt).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [500, 1_000, 2_000, 3_000, 3_000, 3_000]);
+    }
+
+    #[test]
+    fn jitter_stays_within_its_spread() {
+        let policy = RetryPolicy {
+            jitter: 0.2,
+            ..policy()
+        };
+        for _ in 0..100 {
+            let delay = policy.delay(2).as_millis();
+            assert!((800..=1_200).contains(&delay), "{delay}");
+        }
+        assert!((0..1_000).all(|_| (0.0..1.0).contains(&random_unit())));
+    }
+
+    #[test]
+    fn retries_stop_at_the_attempt_limit_and_the_deadline() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            ..policy()
+        };
+        assert_eq!(
+            policy.next_delay(2, None),
+            Some(Duration::from_millis(1_000))
+        );
+        assert_eq!(policy.next_delay(3, None), None);
+
+        let deadline = Instant::now() + Duration::from_millis(800);
+        assert!(policy.next_delay(1, Some(deadline)).is_some());
+        assert_eq!(policy.next_delay(2, Some(deadline)), None);
+        assert_eq!(RetryPolicy::default().next_delay(1, None), None);
+    }
+}
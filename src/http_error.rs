@@ -1,25 +1,35 @@
-use actix_web::error::HttpError;
-use actix_web::http::header::ToStrError;
+pub mod backtrace;
+pub mod codes;
+pub mod context;
+pub mod extractor;
+pub mod i18n;
+pub mod problem;
+pub mod redaction;
+pub mod reporting;
+pub mod validation;
+
+use crate::http_error::codes::ErrorCode;
+use crate::http_error::context::RequestContext;
+use crate::http_error::extractor::ExtractorError;
+use crate::http_error::i18n::REDACTED_KEY;
+use crate::http_error::problem::{ErrorFormat, PROBLEM_JSON, error_format, problem_body};
+use crate::http_error::redaction::{REDACTED_MESSAGE, verbose_errors};
+use crate::http_error::reporting::ErrorReport;
+use crate::http_error::validation::ValidationErrors;
+use actix_web::error::{HttpError, JsonPayloadError};
 use actix_web::http::StatusCode;
+use actix_web::http::header::{CONTENT_LANGUAGE, ToStrError, VARY};
 use actix_web::{HttpResponse, ResponseError};
 use anyhow::anyhow;
 use serde_json::json;
-use std::path::Path;
+use std::fmt::{Debug, Display};
 
 /// Custom error types for handling various error scenarios in the application
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     // Represents unspecified internal errors
     #[allow(dead_code)]
-    #[error(
-        "an unspecified internal error occurred: * `lib.rs` sibling file text :*:
-```rust
-pub mod asset_endpoint;
-pub mod data_database_connection;
-pub mod http_error;
-
-```"
-    )]
+    #[error("an unspecified internal error occurred: {0}")]
     InternalError(anyhow::Error),
 
     // Generic error type for miscellaneous errors
@@ -34,22 +44,237 @@ pub mod http_error;
     // Specific error for header parsing failures
     #[error("unable to parse headers: {0:?}")]
     HeaderParse(ToStrError),
+
+    // The requested resource does not exist
+    #[error("{0}")]
+    NotFound(anyhow::Error),
+
+    // The request lacks valid authentication credentials
+    #[error("{0}")]
+    Unauthorized(anyhow::Error),
+
+    // The caller is authenticated but not allowed to perform the request
+    #[error("{0}")]
+    Forbidden(anyhow::Error),
+
+    // The request conflicts with the current state of the resource
+    #[error("{0}")]
+    Conflict(anyhow::Error),
+
+    // The request is well-formed but its contents are invalid
+    #[error("{0}")]
+    Validation(anyhow::Error),
+
+    // An upstream dependency (e.g. the database) did not respond in time
+    #[error("{0}")]
+    Timeout(anyhow::Error),
+
+    // A dependency is unavailable; the request may be retried later
+    #[error("{0}")]
+    ServiceUnavailable(anyhow::Error),
+
+    // The caller has sent too many requests
+    #[error("{0}")]
+    TooManyRequests(anyhow::Error),
+
+    // Any other status with a message
+    #[error("{1}")]
+    Status(StatusCode, anyhow::Error),
+
+    // Error carrying its own code and status, see `codes::ErrorCode`
+    #[error("{source}")]
+    Coded {
+        code: &'static str,
+        status: StatusCode,
+        params: serde_json::Map<String, serde_json::Value>,
+        source: anyhow::Error,
+    },
+}
+
+impl Error {
+    /// `404 Not Found` with `message`.
+    pub fn not_found<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::NotFound(anyhow::Error::msg(message))
+    }
+
+    /// `401 Unauthorized` with `message`.
+    pub fn unauthorized<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::Unauthorized(anyhow::Error::msg(message))
+    }
+
+    /// `403 Forbidden` with `message`.
+    pub fn forbidden<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::Forbidden(anyhow::Error::msg(message))
+    }
+
+    /// `409 Conflict` with `message`.
+    pub fn conflict<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::Conflict(anyhow::Error::msg(message))
+    }
+
+    /// `422 Unprocessable Entity` with `message`.
+    pub fn validation<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::Validation(anyhow::Error::msg(message))
+    }
+
+    /// `504 Gateway Timeout` with `message`.
+    pub fn timeout<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::Timeout(anyhow::Error::msg(message))
+    }
+
+    /// `503 Service Unavailable` with `message`.
+    pub fn service_unavailable<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::ServiceUnavailable(anyhow::Error::msg(message))
+    }
+
+    /// `429 Too Many Requests` with `message`.
+    pub fn too_many_requests<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::TooManyRequests(anyhow::Error::msg(message))
+    }
+
+    /// `500 Internal Server Error` with `message`.
+    pub fn internal<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+        Self::InternalError(anyhow::Error::msg(message))
+    }
+
+    /// Converts an error type that implements [`ErrorCode`], keeping its code and status.
+    pub fn coded<E: ErrorCode>(err: E) -> Self {
+        Self::Coded {
+            code: err.code(),
+            status: err.status_code(),
+            params: err.params(),
+            source: anyhow::Error::new(err),
+        }
+    }
+
+    /// Returns the stable error code sent to clients. See [`codes`] for the built-in codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InternalError(e) | Self::Other(e) => {
+                sqlx_code(e).unwrap_or(codes::INTERNAL_ERROR)
+            }
+            Self::Anyhow(_) => codes::BAD_REQUEST,
+            Self::HeaderParse(_) => codes::HEADER_PARSE,
+            Self::NotFound(_) => codes::NOT_FOUND,
+            Self::Unauthorized(_) => codes::UNAUTHORIZED,
+            Self::Forbidden(_) => codes::FORBIDDEN,
+            Self::Conflict(e) => sqlx_code(e).unwrap_or(codes::CONFLICT),
+            Self::Validation(_) => codes::VALIDATION,
+            Self::Timeout(e) => sqlx_code(e).unwrap_or(codes::TIMEOUT),
+            Self::ServiceUnavailable(e) => sqlx_code(e).unwrap_or(codes::SERVICE_UNAVAILABLE),
+            Self::TooManyRequests(_) => codes::TOO_MANY_REQUESTS,
+            Self::Status(status, _) => codes::for_status(*status),
+            Self::Coded { code, .. } => code,
+        }
+    }
+
+    /// Extra members describing the error, added to the response body.
+    fn details(&self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        match self {
+            Self::Coded { source, .. } => source
+                .downcast_ref::<ExtractorError>()
+                .map(ExtractorError::details)
+                .or_else(|| {
+                    source
+                        .downcast_ref::<ValidationErrors>()
+                        .map(ValidationErrors::details)
+                }),
+            _ => None,
+        }
+    }
+
+    /// Underlying error, for every variant that wraps one.
+    fn inner(&self) -> Option<&anyhow::Error> {
+        match self {
+            Self::InternalError(err)
+            | Self::Other(err)
+            | Self::Anyhow(err)
+            | Self::NotFound(err)
+            | Self::Unauthorized(err)
+            | Self::Forbidden(err)
+            | Self::Conflict(err)
+            | Self::Validation(err)
+            | Self::Timeout(err)
+            | Self::ServiceUnavailable(err)
+            | Self::TooManyRequests(err)
+            | Self::Status(_, err)
+            | Self::Coded { source: err, .. } => Some(err),
+            Self::HeaderParse(_) => None,
+        }
+    }
+
+    /// Error messages from the outermost to the root cause.
+    fn causes(&self) -> Vec<String> {
+        let chain = match self.inner() {
+            Some(err) => err.chain(),
+            None => anyhow::Chain::new(self),
+        };
+        chain.map(ToString::to_string).collect()
+    }
+
+    /// Frames of the backtrace captured where the error was created, if any.
+    fn stacktrace(&self) -> Vec<backtrace::Frame> {
+        self.inner()
+            .map(|err| backtrace::frames(err.backtrace()))
+            .unwrap_or_default()
+    }
+
+    /// Logs the full error chain of a `5xx` response under `incident_id`.
+    fn log_incident(
+        &self,
+        incident_id: &str,
+        status: StatusCode,
+        context: Option<&RequestContext>,
+    ) {
+        let (method, path) = context
+            .map(|c| (c.method.as_str(), c.path.as_str()))
+            .unwrap_or_default();
+        match self.inner() {
+            Some(err) => {
+                log::error!("Incident {incident_id}: {method} {path} failed with {status}: {err:?}")
+            }
+            None => log::error!(
+                "Incident {incident_id}: {method} {path} failed with {status}: {self:?}"
+            ),
+        }
+    }
+
+    /// Arbitrary `status` with `message`.
+    pub fn status<M: Display + Debug + Send + Sync + 'static>(
+        status: StatusCode,
+        message: M,
+    ) -> Self {
+        Self::Status(status, anyhow::Error::msg(message))
+    }
 }
 
 impl ResponseError for Error {
     fn status_code(&self) -> StatusCode {
         match &self {
             Self::InternalError(_) | Self::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            _ => StatusCode::BAD_REQUEST,
+            Self::Anyhow(_) | Self::HeaderParse(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Self::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Status(status, _) | Self::Coded { status, .. } => *status,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
         let status_code = self.status_code();
 
-        // Extract only the error message without the backtrace
+        // Extract only the error message; the cause chain and backtrace are rendered separately
         let full_error = self.to_string();
-        let error_message = match full_error.split("\n\nStack backtrace:").next() {
+        let error_message = match full_error
+            .split("\n\nStack backtrace:")
+            .next()
+            .and_then(|message| message.split("\n\nCaused by:").next())
+        {
             Some(message) => message.trim(),
             None => &full_error, // Fallback to the full error if split fails
         };
@@ -66,44 +291,131 @@ impl ResponseError for Error {
             _ => error_message,
         };
 
-        #[cfg(debug_assertions)]
-        {
-            // Capture backtrace
-            let backtrace = std::backtrace::Backtrace::capture();
-            let backtrace_str = backtrace.to_string();
+        let context = RequestContext::current();
+        let mut extensions = serde_json::Map::new();
+        extensions.insert("code".to_string(), json!(self.code()));
+        if let Some(details) = self.details() {
+            extensions.extend(details);
+        }
 
-            // Parse backtrace into a structured format
-            let frames = parse_backtrace(&backtrace_str);
+        // Correlate the response with the access log when request IDs are enabled
+        if let Some(request_id) = context.as_ref().and_then(|c| c.request_id.clone()) {
+            extensions.insert("request_id".to_string(), json!(request_id));
+        }
 
-            return HttpResponse::build(status_code)
-                .content_type("application/json")
-                .json(json!({
-                    "message": error_message,
-                    "status": status_code.as_u16(),
-                    "stacktrace": frames
-                }));
+        // Internal errors get an incident ID linking the client's response to the server log, and
+        // are redacted unless verbose errors are enabled
+        let verbose = verbose_errors();
+        let mut error_message = error_message;
+        let mut redacted = false;
+        if status_code.is_server_error() {
+            let incident_id = uuid::Uuid::new_v4().to_string();
+            self.log_incident(&incident_id, status_code, context.as_ref());
+            reporting::dispatch(|| ErrorReport {
+                incident_id: incident_id.clone(),
+                timestamp: reporting::now_millis(),
+                status: status_code.as_u16(),
+                code: self.code(),
+                message: error_message.to_string(),
+                method: context
+                    .as_ref()
+                    .map(|c| c.method.clone())
+                    .unwrap_or_default(),
+                path: context.as_ref().map(|c| c.path.clone()).unwrap_or_default(),
+                request_id: context.as_ref().and_then(|c| c.request_id.clone()),
+                causes: self.causes(),
+                stacktrace: self.stacktrace(),
+            });
+            extensions.insert("incident_id".to_string(), json!(incident_id));
+            if !verbose {
+                error_message = REDACTED_MESSAGE;
+                redacted = true;
+            }
         }
 
-        #[cfg(not(debug_assertions))]
-        {
-            // For production - no stacktrace
-            HttpResponse::build(status_code)
-                .content_type("application/json")
-                .json(json!({
+        // Replace the message with the template of the client's language, if there is one
+        let mut params = match self {
+            Self::Coded { params, .. } => params.clone(),
+            _ => serde_json::Map::new(),
+        };
+        params.insert("message".to_string(), json!(error_message));
+        params.insert("code".to_string(), json!(self.code()));
+        params.insert("status".to_string(), json!(status_code.as_u16()));
+        let key = if redacted { REDACTED_KEY } else { self.code() };
+        let accept_language = context.as_ref().and_then(|c| c.accept_language.as_deref());
+        let (language, error_message) = match i18n::localize(key, &params, accept_language) {
+            Some((language, message)) => (Some(language), message),
+            None => (None, error_message.to_string()),
+        };
+        let error_message = error_message.as_str();
+
+        if verbose {
+            // The backtrace was captured where the error was created, not here
+            let frames = self.stacktrace();
+            if !frames.is_empty() {
+                extensions.insert("stacktrace".to_string(), json!(frames));
+            }
+            extensions.insert("causes".to_string(), json!(self.causes()));
+        }
+
+        // The message was looked up by the client's language, so caches must key on it
+        let mut response = HttpResponse::build(status_code);
+        response.insert_header((VARY, "Accept-Language"));
+        if let Some(language) = language {
+            response.insert_header((CONTENT_LANGUAGE, language));
+        }
+        match error_format().resolve(context.as_ref()) {
+            ErrorFormat::Problem => response.content_type(PROBLEM_JSON).body(
+                problem_body(status_code, error_message, context.as_ref(), extensions).to_string(),
+            ),
+            _ => {
+                let mut body = json!({
                     "message": error_message,
                     "status": status_code.as_u16()
-                }))
+                });
+                if let serde_json::Value::Object(members) = &mut body {
+                    members.extend(extensions);
+                }
+                response.content_type("application/json").json(body)
+            }
         }
     }
 }
 
 /// Conversion from anyhow::Error to custom Error type
+///
+/// Wrapped `sqlx::Error`s and `std::io::Error`s are classified like their direct conversions, so
+/// `?` on an `anyhow::Result` reports database and I/O failures with the same status. Any other
+/// error maps to `400`.
 impl From<anyhow::Error> for Error {
     fn from(err: anyhow::Error) -> Self {
+        if let Some((variant, message)) = err.downcast_ref::<sqlx::Error>().map(classify_sqlx) {
+            return variant(match message {
+                Some(message) => err.context(message),
+                None => err,
+            });
+        }
+        if let Some(variant) = err.downcast_ref::<std::io::Error>().map(classify_io) {
+            return variant(err);
+        }
         Error::Anyhow(err)
     }
 }
 
+/// Conversion from any [`ErrorCode`] type to custom Error type
+impl<E: ErrorCode> From<E> for Error {
+    fn from(err: E) -> Self {
+        Error::coded(err)
+    }
+}
+
+/// Conversion from JsonPayloadError to custom Error type
+impl From<JsonPayloadError> for Error {
+    fn from(err: JsonPayloadError) -> Self {
+        Error::coded(ExtractorError::from_json(&err))
+    }
+}
+
 /// Conversion from ToStrError to custom Error type
 impl From<ToStrError> for Error {
     fn from(err: ToStrError) -> Self {
@@ -111,99 +423,346 @@ impl From<ToStrError> for Error {
     }
 }
 
-/// Conversion from std::io::Error to custom Error type
+/// Conversion from std::io::Error to custom Error type, classified by error kind
+///
+/// Timeouts map to `504` and connection failures to `503`. Everything else, including
+/// `NotFound` and `PermissionDenied`, is a server-side fault and maps to `500`, so the io
+/// message is redacted like any other internal error.
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Error::Anyhow(anyhow::Error::new(err))
+        classify_io(&err)(anyhow::Error::new(err))
     }
 }
 
+/// Variant an io error of this kind is wrapped in.
+fn classify_io(err: &std::io::Error) -> fn(anyhow::Error) -> Error {
+    use std::io::ErrorKind;
+    match err.kind() {
+        ErrorKind::TimedOut => Error::Timeout,
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::NotConnected => Error::ServiceUnavailable,
+        _ => Error::InternalError,
+    }
+}
+
+/// MySQL error numbers reported when a statement times out.
+/// 1205: lock wait timeout exceeded; 3024: maximum statement execution time exceeded.
+const MYSQL_TIMEOUT_ERRORS: [u16; 2] = [1205, 3024];
+
 /// Conversion from sqlx::Error to custom Error type
+///
+/// | sqlx error                                  | Variant              | Status |
+/// |---------------------------------------------|----------------------|--------|
+/// | `RowNotFound`                               | `NotFound`           | 404    |
+/// | unique / foreign key violation              | `Conflict`           | 409    |
+/// | lock wait / statement timeout               | `Timeout`            | 504    |
+/// | `PoolTimedOut`, `PoolClosed`, `Io`, `Tls`   | `ServiceUnavailable` | 503    |
+/// | anything else                               | `InternalError`      | 500    |
+///
+/// The original sqlx error is kept as the source of a client-safe context message.
 impl From<sqlx::Error> for Error {
     fn from(err: sqlx::Error) -> Self {
-        Error::Anyhow(anyhow::Error::new(err))
+        let (variant, message) = classify_sqlx(&err);
+        let err = anyhow::Error::new(err);
+        variant(match message {
+            Some(message) => err.context(message),
+            None => err,
+        })
+    }
+}
+
+/// Variant a sqlx error is wrapped in and the client-safe message added as its context.
+fn classify_sqlx(err: &sqlx::Error) -> (fn(anyhow::Error) -> Error, Option<&'static str>) {
+    match err {
+        sqlx::Error::RowNotFound => (Error::NotFound, Some("The requested record was not found")),
+        sqlx::Error::Database(db_err)
+            if db_err.is_unique_violation() || db_err.is_foreign_key_violation() =>
+        {
+            (
+                Error::Conflict,
+                Some("The request conflicts with an existing record"),
+            )
+        }
+        sqlx::Error::Database(db_err)
+            if db_err
+                .try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+                .is_some_and(|e| MYSQL_TIMEOUT_ERRORS.contains(&e.number())) =>
+        {
+            (Error::Timeout, Some("The database did not respond in time"))
+        }
+        sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_) => (
+            Error::ServiceUnavailable,
+            Some("The database is currently unavailable"),
+        ),
+        _ => (Error::InternalError, None),
     }
 }
 
 /// Conversion from HttpError to custom Error type
 impl From<HttpError> for Error {
     fn from(err: HttpError) -> Self {
-        Error::Anyhow(anyhow::Error::new(err))
+        Error::InternalError(anyhow::Error::new(err))
     }
 }
 
-/// Conversion from HttpResponse to custom Error type
+/// Conversion from HttpResponse to custom Error type, keeping the response status
 impl From<HttpResponse> for Error {
     fn from(err: HttpResponse) -> Self {
-        Error::Anyhow(anyhow!(
-            "HTTP response error: {}",
-            err.status().canonical_reason().unwrap_or("")
-        ))
+        Error::Status(
+            err.status(),
+            anyhow!(
+                "HTTP response error: {}",
+                err.status().canonical_reason().unwrap_or("")
+            ),
+        )
     }
 }
 
+/// Returns the database-specific code for an error converted from `sqlx::Error`.
+fn sqlx_code(err: &anyhow::Error) -> Option<&'static str> {
+    let code = match err.downcast_ref::<sqlx::Error>()? {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => codes::DB_UNIQUE_VIOLATION,
+        sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+            codes::DB_FOREIGN_KEY_VIOLATION
+        }
+        sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_) => codes::DB_UNAVAILABLE,
+        sqlx::Error::RowNotFound => codes::NOT_FOUND,
+        _ if MYSQL_TIMEOUT_ERRORS.iter().any(|n| is_mysql_error(err, *n)) => codes::DB_TIMEOUT,
+        _ => codes::DB_ERROR,
+    };
+    Some(code)
+}
+
+/// Returns `true` if `err` wraps a MySQL database error with error number `number`.
+fn is_mysql_error(err: &anyhow::Error, number: u16) -> bool {
+    matches!(
+        err.downcast_ref::<sqlx::Error>(),
+        Some(sqlx::Error::Database(db_err))
+            if db_err
+                .try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+                .is_some_and(|e| e.number() == number)
+    )
+}
+
 // Type alias for Result using custom Error type
 pub type Result<T> = std::result::Result<T, Error>;
 
-/// Helper to parse the backtrace into structured JSON data
-fn parse_backtrace(backtrace_str: &str) -> Vec<serde_json::Value> {
-    let lines: Vec<&str> = backtrace_str.lines().collect();
-    let mut idx = 0;
-    let mut frames = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::error::{DatabaseError, ErrorKind as DbErrorKind};
+    use std::borrow::Cow;
+    use std::io::{Error as IoError, ErrorKind};
+
+    /// Database error of a given kind, standing in for a MySQL driver error.
+    #[derive(Debug, thiserror::Error)]
+    #[error("Duplicate entry 'a@b.c' for key 'users.email'")]
+    struct FakeDatabaseError(DbErrorKind);
 
-    while idx < lines.len() {
-        let line = lines[idx].trim_start();
+    impl DatabaseError for FakeDatabaseError {
+        fn message(&self) -> &str {
+            "Duplicate entry 'a@b.c' for key 'users.email'"
+        }
 
-        // Match lines that start with frame number ("0:", "1:", ...)
-        if let Some(colon_pos) = line.find(':') {
-            let (frame_number, rest) = line.split_at(colon_pos);
-            if frame_number.trim().chars().all(char::is_numeric) {
-                let function_name = rest[1..].trim();
+        fn code(&self) -> Option<Cow<'_, str>> {
+            None
+        }
 
-                // Get next line for location information
-                idx += 1;
-                if idx < lines.len() {
-                    let location_line = lines[idx].trim().strip_prefix("at ").unwrap_or("");
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
 
-                    let (path_only, line_number) = extract_line_number(location_line);
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
 
-                    let absolute_path = absolute_path(path_only);
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
 
-                    frames.push(json!({
-                        "file": absolute_path,
-                        "function": function_name,
-                        "line": line_number,
-                    }));
-                }
+        fn kind(&self) -> DbErrorKind {
+            match self.0 {
+                DbErrorKind::UniqueViolation => DbErrorKind::UniqueViolation,
+                DbErrorKind::ForeignKeyViolation => DbErrorKind::ForeignKeyViolation,
+                _ => DbErrorKind::Other,
             }
         }
-        idx += 1;
     }
 
-    frames
-}
+    fn sqlx_errors() -> Vec<(sqlx::Error, StatusCode, &'static str)> {
+        vec![
+            (
+                sqlx::Error::RowNotFound,
+                StatusCode::NOT_FOUND,
+                codes::NOT_FOUND,
+            ),
+            (
+                sqlx::Error::Database(Box::new(FakeDatabaseError(DbErrorKind::UniqueViolation))),
+                StatusCode::CONFLICT,
+                codes::DB_UNIQUE_VIOLATION,
+            ),
+            (
+                sqlx::Error::Database(Box::new(FakeDatabaseError(
+                    DbErrorKind::ForeignKeyViolation,
+                ))),
+                StatusCode::CONFLICT,
+                codes::DB_FOREIGN_KEY_VIOLATION,
+            ),
+            (
+                sqlx::Error::Database(Box::new(FakeDatabaseError(DbErrorKind::Other))),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                codes::DB_ERROR,
+            ),
+            (
+                sqlx::Error::PoolTimedOut,
+                StatusCode::SERVICE_UNAVAILABLE,
+                codes::DB_UNAVAILABLE,
+            ),
+            (
+                sqlx::Error::PoolClosed,
+                StatusCode::SERVICE_UNAVAILABLE,
+                codes::DB_UNAVAILABLE,
+            ),
+            (
+                sqlx::Error::Io(IoError::from(ErrorKind::ConnectionReset)),
+                StatusCode::SERVICE_UNAVAILABLE,
+                codes::DB_UNAVAILABLE,
+            ),
+            (
+                sqlx::Error::Protocol("unexpected packet".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                codes::DB_ERROR,
+            ),
+        ]
+    }
+
+    /// Serializes the tests that change the process-wide verbosity.
+    static VERBOSITY: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    async fn render_internal_error() -> serde_json::Value {
+        use actix_web::test::{TestRequest, call_and_read_body_json, init_service};
+        use actix_web::{App, web};
 
-/// Extract file path and line number from string
-fn extract_line_number(location: &str) -> (String, i32) {
-    if let Some((path, line_number_str)) = location.rsplit_once(':') {
-        if let Ok(line_number) = line_number_str.parse::<i32>() {
-            return (path.to_string(), line_number);
+        let app = init_service(App::new().default_service(web::to(|| async {
+            Err::<HttpResponse, _>(Error::InternalError(
+                anyhow!("Unknown column 'secret' in 'users'").context("Failed to load the user"),
+            ))
+        })))
+        .await;
+        call_and_read_body_json(&app, TestRequest::get().uri("/users/1").to_request()).await
+    }
+
+    #[actix_web::test]
+    async fn internal_errors_are_redacted() {
+        let _verbosity = VERBOSITY.lock().await;
+        redaction::set_verbose_errors(false);
+
+        let body = render_internal_error().await;
+        assert_eq!(body["status"], 500);
+        assert_eq!(body["message"], REDACTED_MESSAGE);
+        assert!(
+            body["incident_id"]
+                .as_str()
+                .is_some_and(|id| !id.is_empty())
+        );
+        assert!(body.get("causes").is_none());
+        assert!(body.get("stacktrace").is_none());
+        assert!(!body.to_string().contains("secret"));
+    }
+
+    #[actix_web::test]
+    async fn verbose_errors_include_causes_and_stacktrace() {
+        let _verbosity = VERBOSITY.lock().await;
+        redaction::set_verbose_errors(true);
+
+        let body = render_internal_error().await;
+        redaction::set_verbose_errors(false);
+        assert_eq!(body["status"], 500);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("Failed to load the user")
+        );
+        assert!(body["incident_id"].is_string());
+        assert_eq!(
+            body["causes"],
+            json!([
+                "Failed to load the user",
+                "Unknown column 'secret' in 'users'"
+            ])
+        );
+        // anyhow only captures backtraces when enabled through RUST_LIB_BACKTRACE/RUST_BACKTRACE
+        let captured = std::backtrace::Backtrace::capture().status()
+            == std::backtrace::BacktraceStatus::Captured;
+        assert_eq!(body["stacktrace"].is_array(), captured);
+    }
+
+    #[test]
+    fn error_responses_vary_by_language() {
+        let response = Error::not_found("no such user").error_response();
+        assert_eq!(response.headers().get(VARY).unwrap(), "Accept-Language");
+    }
+
+    #[test]
+    fn sqlx_errors_are_classified() {
+        for (err, status, code) in sqlx_errors() {
+            let name = format!("{err:?}");
+            let error = Error::from(err);
+            assert_eq!(error.status_code(), status, "{name}");
+            assert_eq!(error.code(), code, "{name}");
         }
     }
-    (location.to_string(), -1)
-}
 
-/// Convert path to absolute path
-fn absolute_path(path: String) -> String {
-    if path.starts_with(".") {
-        // Handle relative path starting from current directory
-        std::env::current_dir()
-            .map(|p| p.join(path).canonicalize().unwrap_or_default())
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string()
-    } else {
-        // Path is already absolute; just clean it up
-        Path::new(&path).to_path_buf().to_string_lossy().to_string()
+    #[test]
+    fn anyhow_wrapped_errors_are_classified() {
+        for (err, status, code) in sqlx_errors() {
+            let name = format!("{err:?}");
+            let error = Error::from(anyhow::Error::new(err).context("Failed to load the user"));
+            assert_eq!(error.status_code(), status, "{name}");
+            assert_eq!(error.code(), code, "{name}");
+        }
+
+        let error = Error::from(anyhow::Error::new(sqlx::Error::Database(Box::new(
+            FakeDatabaseError(DbErrorKind::UniqueViolation),
+        ))));
+        assert_eq!(
+            error.to_string(),
+            "The request conflicts with an existing record"
+        );
+
+        let error = Error::from(anyhow::Error::new(IoError::from(ErrorKind::NotFound)));
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let error = Error::from(anyhow!("Missing query parameter"));
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn io_errors_are_server_errors() {
+        for (kind, status) in [
+            (ErrorKind::NotFound, StatusCode::INTERNAL_SERVER_ERROR),
+            (
+                ErrorKind::PermissionDenied,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (ErrorKind::InvalidData, StatusCode::INTERNAL_SERVER_ERROR),
+            (ErrorKind::TimedOut, StatusCode::GATEWAY_TIMEOUT),
+            (
+                ErrorKind::ConnectionRefused,
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        ] {
+            let error = Error::from(IoError::new(kind, "/srv/data/prices.csv"));
+            assert_eq!(error.status_code(), status, "{kind:?}");
+        }
     }
 }
@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether runtime frames are dropped from rendered stack traces.
+static FILTER_FRAMES: AtomicBool = AtomicBool::new(true);
+
+/// Crate prefixes of frames considered runtime plumbing rather than application code.
+const RUNTIME_CRATES: [&str; 14] = [
+    "std::",
+    "core::",
+    "alloc::",
+    "tokio::",
+    "actix_web::",
+    "actix_http::",
+    "actix_server::",
+    "actix_service::",
+    "actix_rt::",
+    "actix_cors::",
+    "futures_util::",
+    "futures_core::",
+    "anyhow::",
+    "__rust",
+];
+
+/// One frame of a rendered stack trace.
+#[derive(Clone, Debug, Serialize)]
+pub struct Frame {
+    /// Demangled function name.
+    pub function: String,
+    /// Source file, made absolute when it was recorded relative to the working directory.
+    pub file: Option<String>,
+    /// Line in `file`.
+    pub line: Option<u32>,
+    /// Column in `file`.
+    pub column: Option<u32>,
+}
+
+/// Selects whether std, tokio, actix and anyhow frames are dropped from rendered stack traces.
+/// Enabled by default.
+pub fn set_filter_frames(filter: bool) {
+    FILTER_FRAMES.store(filter, Ordering::Relaxed);
+}
+
+/// Returns the frames of `backtrace`, or nothing if it was not captured.
+///
+/// Backtraces are captured by `anyhow` when the error is created, which requires
+/// `RUST_LIB_BACKTRACE=1` (or `RUST_BACKTRACE=1`).
+pub(crate) fn frames(backtrace: &Backtrace) -> Vec<Frame> {
+    if backtrace.status() != BacktraceStatus::Captured {
+        return Vec::new();
+    }
+    let filter = FILTER_FRAMES.load(Ordering::Relaxed);
+    parse(&backtrace.to_string())
+        .into_iter()
+        .filter(|frame| !filter || !is_runtime_frame(&frame.function))
+        .collect()
+}
+
+/// Parses the `Display` output of a captured backtrace:
+///
+/// ```text
+///    4: my_app::handlers::get_user
+///              at ./src/handlers.rs:42:9
+/// ```
+///
+/// Frames without symbol information have no `at` line and keep an unknown location.
+///
+/// The text is the only stable view of the frames: the trace is the `std::backtrace::Backtrace`
+/// captured by `anyhow`, whose `frames()` is still unstable, and the `backtrace` crate cannot
+/// walk a trace it did not capture itself. Capturing a second trace would point at the place the
+/// response is rendered rather than where the error was created.
+fn parse(backtrace: &str) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = Vec::new();
+    for line in backtrace.lines().map(str::trim) {
+        if let Some(location) = line.strip_prefix("at ") {
+            if let Some(frame) = frames.last_mut()
+                && frame.file.is_none()
+            {
+                let (file, line, column) = split_location(location);
+                frame.file = Some(absolute_path(file));
+                frame.line = line;
+                frame.column = column;
+            }
+        } else if let Some((index, function)) = line.split_once(": ")
+            && !index.is_empty()
+            && index.chars().all(|c| c.is_ascii_digit())
+        {
+            frames.push(Frame {
+                function: function.to_string(),
+                file: None,
+                line: None,
+                column: None,
+            });
+        }
+    }
+    frames
+}
+
+/// Splits `path:line:column` into its parts. Missing or non-numeric parts stay with the path.
+fn split_location(location: &str) -> (&str, Option<u32>, Option<u32>) {
+    let Some((rest, last)) = location.rsplit_once(':') else {
+        return (location, None, None);
+    };
+    let Ok(last) = last.parse() else {
+        return (location, None, None);
+    };
+    match rest.rsplit_once(':') {
+        Some((path, line)) => match line.parse() {
+            Ok(line) => (path, Some(line), Some(last)),
+            Err(_) => (rest, Some(last), None),
+        },
+        None => (rest, Some(last), None),
+    }
+}
+
+/// Returns `true` for frames of the standard library, the async runtime, actix and anyhow,
+/// including trait impls on their types (`<core::pin::Pin<P> as core::future::Future>::poll`)
+/// and blanket impls over a generic parameter (`<T as core::convert::Into<U>>::into`). Frames
+/// without symbols are dropped too.
+fn is_runtime_frame(function: &str) -> bool {
+    if function == "<unknown>" {
+        return true;
+    }
+    let is_runtime = |path: &str| RUNTIME_CRATES.iter().any(|prefix| path.starts_with(prefix));
+    let Some(qualified) = function.strip_prefix('<') else {
+        return is_runtime(function);
+    };
+    match qualified.split_once(" as ") {
+        Some((ty, tr)) if ty.len() == 1 && ty.chars().all(|c| c.is_ascii_uppercase()) => {
+            is_runtime(tr)
+        }
+        Some((ty, _)) => is_runtime(ty),
+        None => is_runtime(qualified),
+    }
+}
+
+/// Converts a path relative to the working directory to an absolute one.
+fn absolute_path(path: &str) -> String {
+    if path.starts_with('.') {
+        std::env::current_dir()
+            .and_then(|dir| dir.join(path).canonicalize())
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string())
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `Display` output of a std backtrace, trimmed to the interesting frames.
+    const SAMPLE: &str = "   0: std::backtrace_rs::backtrace::libunwind::trace
+             at /rustc/59807616e/library/std/src/../../backtrace/src/backtrace/libunwind.rs:117:9
+   1: anyhow::error::<impl anyhow::Error>::msg
+             at /cargo/registry/src/anyhow-1.0.98/src/error.rs:83:36
+   2: my_app::handlers::get_user::{{closure}}
+             at /srv/my_app/src/handlers.rs:42:9
+   3: <my_app::store::Store as my_app::store::Lookup>::find
+             at /srv/my_app/src/store.rs:7
+   4: <core::pin::Pin<P> as core::future::Future>::poll
+             at /rustc/59807616e/library/core/src/future/future.rs:124:9
+   5: <T as core::convert::Into<U>>::into
+             at /rustc/59807616e/library/core/src/convert/mod.rs:759:9
+   6: <actix_web::handler::HandlerServiceFuture<F, T, R> as core::future::Future>::poll
+   7: <unknown>
+   8: __rust_begin_short_backtrace
+";
+
+    #[test]
+    fn frames_are_parsed_with_their_locations() {
+        let frames = parse(SAMPLE);
+        assert_eq!(frames.len(), 9);
+        assert_eq!(
+            frames[2].function,
+            "my_app::handlers::get_user::{{closure}}"
+        );
+        assert_eq!(
+            frames[2].file.as_deref(),
+            Some("/srv/my_app/src/handlers.rs")
+        );
+        assert_eq!((frames[2].line, frames[2].column), (Some(42), Some(9)));
+        assert_eq!(
+            frames[3].function,
+            "<my_app::store::Store as my_app::store::Lookup>::find"
+        );
+        assert_eq!((frames[3].line, frames[3].column), (Some(7), None));
+        assert_eq!(frames[6].file, None);
+        assert_eq!(frames[7].function, "<unknown>");
+    }
+
+    #[test]
+    fn runtime_frames_are_recognized() {
+        let application = parse(SAMPLE)
+            .into_iter()
+            .filter(|frame| !is_runtime_frame(&frame.function))
+            .map(|frame| frame.function)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            application,
+            [
+                "my_app::handlers::get_user::{{closure}}",
+                "<my_app::store::Store as my_app::store::Lookup>::find",
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Stable, machine-readable error codes included as `code` in every error body.
+//!
+//! Clients should branch on these instead of the human-readable `message`. Codes are never
+//! renamed once published; new codes may be added.
+//!
+//! Downstream crates add their own codes by implementing [`ErrorCode`] on an error type and
+//! converting it with [`Error::coded`](super::Error::coded) (or `?`).
+
+use actix_web::http::StatusCode;
+use serde_json::{Map, Value};
+
+/// An unexpected server-side failure.
+pub const INTERNAL_ERROR: &str = "INTERNAL_ERROR";
+/// A generic client error without a more specific code.
+pub const BAD_REQUEST: &str = "BAD_REQUEST";
+/// A request header contained non-visible-ASCII characters.
+pub const HEADER_PARSE: &str = "HEADER_PARSE";
+/// The request body was not valid JSON or did not match the expected shape.
+pub const JSON_PARSE: &str = "JSON_PARSE";
+/// A `web::Path` parameter could not be parsed.
+pub const PATH_PARSE: &str = "PATH_PARSE";
+/// A `web::Query` parameter was missing or could not be parsed.
+pub const QUERY_PARSE: &str = "QUERY_PARSE";
+/// A `web::Form` body was malformed or a field could not be parsed.
+pub const FORM_PARSE: &str = "FORM_PARSE";
+/// The request body exceeded the configured size limit.
+pub const PAYLOAD_TOO_LARGE: &str = "PAYLOAD_TOO_LARGE";
+/// The requested resource does not exist.
+pub const NOT_FOUND: &str = "NOT_FOUND";
+/// The request lacks valid authentication credentials.
+pub const UNAUTHORIZED: &str = "UNAUTHORIZED";
+/// The caller is not allowed to perform the request.
+pub const FORBIDDEN: &str = "FORBIDDEN";
+/// The request conflicts with the current state of the resource.
+pub const CONFLICT: &str = "CONFLICT";
+/// The request contents failed validation.
+pub const VALIDATION: &str = "VALIDATION";
+/// An upstream dependency did not respond in time.
+pub const TIMEOUT: &str = "TIMEOUT";
+/// A dependency is unavailable; retry later.
+pub const SERVICE_UNAVAILABLE: &str = "SERVICE_UNAVAILABLE";
+/// The caller has sent too many requests.
+pub const TOO_MANY_REQUESTS: &str = "TOO_MANY_REQUESTS";
+/// An insert or update violated a unique constraint.
+pub const DB_UNIQUE_VIOLATION: &str = "DB_UNIQUE_VIOLATION";
+/// An insert, update or delete violated a foreign key constraint.
+pub const DB_FOREIGN_KEY_VIOLATION: &str = "DB_FOREIGN_KEY_VIOLATION";
+/// A database statement timed out.
+pub const DB_TIMEOUT: &str = "DB_TIMEOUT";
+/// The database could not be reached or the pool is exhausted.
+pub const DB_UNAVAILABLE: &str = "DB_UNAVAILABLE";
+/// Any other database failure.
+pub const DB_ERROR: &str = "DB_ERROR";
+
+/// Error types that carry their own stable error code.
+///
+/// ```norust
+/// #[derive(thiserror::Error, Debug)]
+/// #[error("store {0} is closed")]
+/// struct StoreClosed(u32);
+///
+/// impl ErrorCode for StoreClosed {
+///     fn code(&self) -> &'static str { "STORE_CLOSED" }
+///     fn status_code(&self) -> StatusCode { StatusCode::CONFLICT }
+/// }
+///
+/// async fn handler() -> http_error::Result<HttpResponse> {
+///     Err(StoreClosed(12))?
+/// }
+/// ```
+pub trait ErrorCode: std::error::Error + Send + Sync + 'static {
+    /// Stable code, conventionally `SCREAMING_SNAKE_CASE`.
+    fn code(&self) -> &'static str;
+
+    /// HTTP status of the response. Defaults to `500 Internal Server Error`.
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    /// Parameters of the localized message template of [`code`](ErrorCode::code), see
+    /// [`i18n`](super::i18n). Defaults to none.
+    fn params(&self) -> Map<String, Value> {
+        Map::new()
+    }
+}
+
+/// Returns the built-in code matching `status`.
+pub(crate) fn for_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::NOT_FOUND => NOT_FOUND,
+        StatusCode::UNAUTHORIZED => UNAUTHORIZED,
+        StatusCode::FORBIDDEN => FORBIDDEN,
+        StatusCode::CONFLICT => CONFLICT,
+        StatusCode::UNPROCESSABLE_ENTITY => VALIDATION,
+        StatusCode::GATEWAY_TIMEOUT | StatusCode::REQUEST_TIMEOUT => TIMEOUT,
+        StatusCode::SERVICE_UNAVAILABLE => SERVICE_UNAVAILABLE,
+        StatusCode::TOO_MANY_REQUESTS => TOO_MANY_REQUESTS,
+        status if status.is_client_error() => BAD_REQUEST,
+        _ => INTERNAL_ERROR,
+    }
+}
@@ -0,0 +1,36 @@
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT: RequestContext;
+}
+
+/// Details of the request currently being handled.
+///
+/// Set for the duration of each request by the server's request-context middleware so that
+/// [`Error::error_response`](actix_web::ResponseError::error_response), which has no access to
+/// the request, can still correlate its output with it.
+#[derive(Clone, Debug, Default)]
+pub struct RequestContext {
+    /// Request ID echoed in the `X-Request-Id` response header, if request IDs are enabled.
+    pub request_id: Option<String>,
+    /// HTTP method.
+    pub method: String,
+    /// Request path, without the query string.
+    pub path: String,
+    /// Value of the `Accept` header, used to negotiate the error body format.
+    pub accept: Option<String>,
+    /// Value of the `Accept-Language` header, used to localize error messages.
+    pub accept_language: Option<String>,
+}
+
+impl RequestContext {
+    /// Returns a copy of the context of the request being handled on this task, if any.
+    pub fn current() -> Option<RequestContext> {
+        CURRENT.try_with(|context| context.clone()).ok()
+    }
+
+    /// Runs `future` with this context as the current one.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+}
@@ -0,0 +1,191 @@
+use crate::http_error::codes::{self, ErrorCode};
+use actix_web::HttpRequest;
+use actix_web::error::{
+    JsonPayloadError, PathError, PayloadError, QueryPayloadError, UrlencodedError,
+};
+use actix_web::http::StatusCode;
+use serde_json::{Map, Value, json};
+use std::fmt;
+
+/// Part of the request an extractor failed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtractorSource {
+    Path,
+    Query,
+    Form,
+    Json,
+    Payload,
+}
+
+impl fmt::Display for ExtractorSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Path => "path",
+            Self::Query => "query",
+            Self::Form => "form",
+            Self::Json => "json",
+            Self::Payload => "payload",
+        })
+    }
+}
+
+/// Failure of one of actix's built-in extractors (`web::Path`, `web::Query`, `web::Form`,
+/// `web::Json`) or of the payload size limit.
+///
+/// Rendered like every other [`Error`](super::Error), with the extra members `source`, `field`
+/// (when the offending field or parameter can be determined) and `reason`:
+///
+/// ```json
+/// {
+///   "code": "QUERY_PARSE",
+///   "status": 400,
+///   "message": "Invalid query parameter `page`: invalid digit found in string",
+///   "source": "query",
+///   "field": "page",
+///   "reason": "invalid digit found in string"
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ExtractorError {
+    /// Part of the request that failed to extract.
+    pub source: ExtractorSource,
+    /// Offending field or parameter, when known.
+    pub field: Option<String>,
+    /// Why extraction failed.
+    pub reason: String,
+    status: StatusCode,
+    code: &'static str,
+}
+
+impl ExtractorError {
+    fn new(source: ExtractorSource, field: Option<String>, reason: String) -> Self {
+        let code = match source {
+            ExtractorSource::Path => codes::PATH_PARSE,
+            ExtractorSource::Query => codes::QUERY_PARSE,
+            ExtractorSource::Form => codes::FORM_PARSE,
+            ExtractorSource::Json => codes::JSON_PARSE,
+            ExtractorSource::Payload => codes::PAYLOAD_TOO_LARGE,
+        };
+        let status = match source {
+            ExtractorSource::Payload => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self {
+            source,
+            field,
+            reason,
+            status,
+            code,
+        }
+    }
+
+    /// Error for a request body larger than the configured limit.
+    pub fn payload_too_large(reason: impl Into<String>) -> Self {
+        Self::new(ExtractorSource::Payload, None, reason.into())
+    }
+
+    /// Converts a `web::Path` failure, naming the path parameter whose value could not be parsed.
+    pub fn from_path(err: &PathError, req: &HttpRequest) -> Self {
+        let reason = match err {
+            PathError::Deserialize(inner) => inner.to_string(),
+            other => other.to_string(),
+        };
+        let field = quoted_field(&reason).or_else(|| {
+            // Path deserialization errors quote the offending value rather than the name.
+            req.match_info()
+                .iter()
+                .find(|(_, value)| reason.contains(&format!("\"{value}\"")))
+                .map(|(name, _)| name.to_string())
+        });
+        Self::new(ExtractorSource::Path, field, reason)
+    }
+
+    /// Converts a `web::Query` failure.
+    pub fn from_query(err: &QueryPayloadError) -> Self {
+        let reason = match err {
+            QueryPayloadError::Deserialize(inner) => inner.to_string(),
+            other => other.to_string(),
+        };
+        Self::new(ExtractorSource::Query, quoted_field(&reason), reason)
+    }
+
+    /// Converts a `web::Form` failure. Oversize bodies become `413 Payload Too Large`.
+    pub fn from_form(err: &UrlencodedError) -> Self {
+        match err {
+            UrlencodedError::Overflow { .. } | UrlencodedError::Payload(PayloadError::Overflow) => {
+                Self::payload_too_large(err.to_string())
+            }
+            UrlencodedError::Parse(inner) => {
+                let reason = inner.to_string();
+                Self::new(ExtractorSource::Form, quoted_field(&reason), reason)
+            }
+            other => Self::new(ExtractorSource::Form, None, other.to_string()),
+        }
+    }
+
+    /// Converts a `web::Json` failure. Oversize bodies become `413 Payload Too Large`.
+    pub fn from_json(err: &JsonPayloadError) -> Self {
+        match err {
+            JsonPayloadError::Overflow { .. }
+            | JsonPayloadError::OverflowKnownLength { .. }
+            | JsonPayloadError::Payload(PayloadError::Overflow) => {
+                Self::payload_too_large(err.to_string())
+            }
+            JsonPayloadError::Deserialize(inner) => {
+                let reason = inner.to_string();
+                Self::new(ExtractorSource::Json, quoted_field(&reason), reason)
+            }
+            other => Self::new(ExtractorSource::Json, None, other.to_string()),
+        }
+    }
+
+    /// Extension members added to the error body.
+    pub(crate) fn details(&self) -> Map<String, Value> {
+        let mut details = Map::new();
+        details.insert("source".to_string(), json!(self.source.to_string()));
+        if let Some(field) = &self.field {
+            details.insert("field".to_string(), json!(field));
+        }
+        details.insert("reason".to_string(), json!(self.reason));
+        details
+    }
+}
+
+impl fmt::Display for ExtractorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.source {
+            ExtractorSource::Path => "path parameter",
+            ExtractorSource::Query => "query parameter",
+            ExtractorSource::Form => "form field",
+            ExtractorSource::Json => "JSON field",
+            ExtractorSource::Payload => return write!(f, "Payload too large: {}", self.reason),
+        };
+        match &self.field {
+            Some(field) => write!(f, "Invalid {kind} `{field}`: {}", self.reason),
+            None => write!(f, "Invalid {}: {}", self.source, self.reason),
+        }
+    }
+}
+
+impl std::error::Error for ExtractorError {}
+
+impl ErrorCode for ExtractorError {
+    fn code(&self) -> &'static str {
+        self.code
+    }
+
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn params(&self) -> Map<String, Value> {
+        self.details()
+    }
+}
+
+/// Returns the first back-tick quoted name in a serde error message, e.g. ``missing field `id` ``.
+fn quoted_field(message: &str) -> Option<String> {
+    let (_, rest) = message.split_once('`')?;
+    let (field, _) = rest.split_once('`')?;
+    (!field.is_empty()).then(|| field.to_string())
+}
@@ -0,0 +1,229 @@
+use anyhow::Context;
+use include_dir::{Dir, include_dir};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+/// Locale used when none of the request's `Accept-Language` ranges has a catalog.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Catalog key of the message sent in place of a redacted `5xx` error.
+pub const REDACTED_KEY: &str = "REDACTED";
+
+/// Catalogs shipped with the library.
+static BUILTIN_CATALOGS: Dir = include_dir!("$CARGO_MANIFEST_DIR/locales");
+
+/// Message templates by lowercase language tag, then error code.
+static CATALOGS: LazyLock<RwLock<HashMap<String, HashMap<String, String>>>> = LazyLock::new(|| {
+    let mut catalogs = HashMap::new();
+    if let Err(e) = merge_dir(&mut catalogs, &BUILTIN_CATALOGS) {
+        log::error!("Failed to load built-in message catalogs: {e:#}");
+    }
+    RwLock::new(catalogs)
+});
+
+/// Loads every `<language>.json` file of `dir` as a message catalog, e.g. `es.json` or
+/// `es-mx.json`, each an object mapping error codes to templates:
+///
+/// ```json
+/// { "STORE_CLOSED": "La tienda {store} está cerrada" }
+/// ```
+///
+/// Templates are merged into the existing catalogs, replacing built-in templates of the same
+/// code. `{name}` placeholders are filled from the error's parameters (see
+/// [`ErrorCode::params`](super::codes::ErrorCode::params)) plus `message`, `code` and `status`;
+/// `{{` and `}}` render literal braces.
+pub fn load_catalogs(dir: &Dir) -> anyhow::Result<()> {
+    let mut catalogs = CATALOGS.write().unwrap_or_else(|e| e.into_inner());
+    merge_dir(&mut catalogs, dir)
+}
+
+/// Adds `templates` to the catalog of `language`, replacing templates of the same code.
+pub fn add_catalog(language: &str, templates: HashMap<String, String>) {
+    CATALOGS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(language.to_ascii_lowercase())
+        .or_default()
+        .extend(templates);
+}
+
+fn merge_dir(
+    catalogs: &mut HashMap<String, HashMap<String, String>>,
+    dir: &Dir,
+) -> anyhow::Result<()> {
+    for file in dir.files() {
+        let path = file.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(language) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let templates: HashMap<String, String> = serde_json::from_slice(file.contents())
+            .with_context(|| format!("Invalid message catalog {}", path.display()))?;
+        catalogs
+            .entry(language.to_ascii_lowercase())
+            .or_default()
+            .extend(templates);
+    }
+    Ok(())
+}
+
+/// Renders the template of `key` for the best language of `accept_language`.
+///
+/// Falls back to [`DEFAULT_LOCALE`] when the preferred languages have no template for `key`, and
+/// returns `None` when no template applies or a placeholder has no parameter, in which case the
+/// error's own message is used. Returns the language used with the message.
+pub(crate) fn localize(
+    key: &str,
+    params: &Map<String, Value>,
+    accept_language: Option<&str>,
+) -> Option<(String, String)> {
+    let catalogs = CATALOGS.read().unwrap_or_else(|e| e.into_inner());
+    preferred_languages(accept_language.unwrap_or_default())
+        .into_iter()
+        .chain(std::iter::once(DEFAULT_LOCALE.to_string()))
+        .find_map(|language| {
+            let template = catalogs.get(&language)?.get(key)?;
+            Some((language, render(template, params)?))
+        })
+}
+
+/// Language tags of an `Accept-Language` header by descending quality, each followed by its
+/// primary subtag (`es-MX` then `es`). Wildcards and rejected (`q=0`) ranges are skipped.
+fn preferred_languages(accept_language: &str) -> Vec<String> {
+    let mut ranges: Vec<(f32, String)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let tag = parts.next()?.to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((quality, tag))
+        })
+        .collect();
+    // Stable sort keeps header order between equal qualities
+    ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut languages = Vec::new();
+    for (_, tag) in ranges {
+        let primary = tag.split('-').next().unwrap_or_default().to_string();
+        for language in [tag, primary] {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+    }
+    languages
+}
+
+/// Replaces `{name}` placeholders in `template`. Returns `None` if a placeholder has no value.
+fn render(template: &str, params: &Map<String, Value>) -> Option<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let brace = &rest[start..];
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            output.push_str(&brace[..1]);
+            rest = &brace[2..];
+            continue;
+        }
+        if let Some(after) = brace.strip_prefix('}') {
+            output.push('}');
+            rest = after;
+            continue;
+        }
+        let end = brace.find('}')?;
+        match params.get(&brace[1..end])? {
+            Value::String(value) => output.push_str(value),
+            value => output.push_str(&value.to_string()),
+        }
+        rest = &brace[end + 1..];
+    }
+    output.push_str(rest);
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(message: &str) -> Map<String, Value> {
+        let mut params = Map::new();
+        params.insert("message".to_string(), json!(message));
+        params.insert("status".to_string(), json!(404));
+        params
+    }
+
+    #[test]
+    fn languages_are_ordered_by_quality() {
+        assert_eq!(
+            preferred_languages("fr;q=0.5, es-MX, *;q=0.8, de;q=0, en;q=0.9"),
+            ["es-mx", "es", "en", "fr"]
+        );
+        assert!(preferred_languages("").is_empty());
+    }
+
+    #[test]
+    fn regions_fall_back_to_their_language_then_the_default() {
+        add_catalog(
+            "es-MX",
+            HashMap::from([("I18N_TEST_REGION".to_string(), "mx".to_string())]),
+        );
+        add_catalog(
+            "es",
+            HashMap::from([
+                ("I18N_TEST_REGION".to_string(), "es".to_string()),
+                (
+                    "I18N_TEST_LANGUAGE".to_string(),
+                    "es: {message}".to_string(),
+                ),
+            ]),
+        );
+        add_catalog(
+            DEFAULT_LOCALE,
+            HashMap::from([("I18N_TEST_DEFAULT".to_string(), "en".to_string())]),
+        );
+
+        let localize = |key| localize(key, &params("gone"), Some("es-MX"));
+        assert_eq!(
+            localize("I18N_TEST_REGION"),
+            Some(("es-mx".into(), "mx".into()))
+        );
+        assert_eq!(
+            localize("I18N_TEST_LANGUAGE"),
+            Some(("es".into(), "es: gone".into()))
+        );
+        assert_eq!(
+            localize("I18N_TEST_DEFAULT"),
+            Some(("en".into(), "en".into()))
+        );
+        assert_eq!(localize("I18N_TEST_MISSING"), None);
+    }
+
+    #[test]
+    fn builtin_templates_keep_the_handler_message() {
+        let (language, message) =
+            localize("NOT_FOUND", &params("no such user"), Some("es")).unwrap();
+        assert_eq!(language, "es");
+        assert_eq!(
+            message,
+            "No se encontró el recurso solicitado: no such user"
+        );
+    }
+
+    #[test]
+    fn templates_escape_braces() {
+        let params = params("gone");
+        assert_eq!(
+            render("{{literal}} {message} ({status})}}", &params).as_deref(),
+            Some("{literal} gone (404)}")
+        );
+        assert_eq!(render("{unknown}", &params), None);
+    }
+}
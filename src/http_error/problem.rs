@@ -0,0 +1,100 @@
+use crate::http_error::context::RequestContext;
+use actix_web::http::StatusCode;
+use serde_json::{Map, Value, json};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Media type of RFC 7807 problem details.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Globally selected error body format, stored as the enum discriminant.
+static ERROR_FORMAT: AtomicU8 = AtomicU8::new(ErrorFormat::Json as u8);
+
+/// Body format used by [`Error::error_response`](actix_web::ResponseError::error_response).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorFormat {
+    /// The library's `{ "message", "status", ... }` object as `application/json`.
+    #[default]
+    Json = 0,
+    /// RFC 7807 problem details as `application/problem+json`.
+    Problem = 1,
+    /// Problem details when the request's `Accept` header lists `application/problem+json`,
+    /// otherwise [`ErrorFormat::Json`].
+    Negotiate = 2,
+}
+
+impl ErrorFormat {
+    /// Returns the format to render for the current request, resolving
+    /// [`ErrorFormat::Negotiate`] against its `Accept` header.
+    pub(crate) fn resolve(self, context: Option<&RequestContext>) -> ErrorFormat {
+        match self {
+            ErrorFormat::Negotiate => {
+                let accepts_problem = context
+                    .and_then(|c| c.accept.as_deref())
+                    .is_some_and(accepts_problem_json);
+                if accepts_problem {
+                    ErrorFormat::Problem
+                } else {
+                    ErrorFormat::Json
+                }
+            }
+            format => format,
+        }
+    }
+}
+
+/// Selects the error body format for the whole process.
+pub fn set_error_format(format: ErrorFormat) {
+    ERROR_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// Returns the error body format selected with [`set_error_format`].
+pub fn error_format() -> ErrorFormat {
+    match ERROR_FORMAT.load(Ordering::Relaxed) {
+        1 => ErrorFormat::Problem,
+        2 => ErrorFormat::Negotiate,
+        _ => ErrorFormat::Json,
+    }
+}
+
+/// Returns `true` if `accept` lists `application/problem+json` with a non-zero quality.
+fn accepts_problem_json(accept: &str) -> bool {
+    accept.split(',').any(|range| {
+        let mut parts = range.split(';').map(str::trim);
+        let media_type = parts.next().unwrap_or_default();
+        let rejected = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q <= 0.0)
+        });
+        media_type.eq_ignore_ascii_case(PROBLEM_JSON) && !rejected
+    })
+}
+
+/// Builds an RFC 7807 problem details object.
+///
+/// `type` is `about:blank`, so `title` is the status' reason phrase. `instance` is the request
+/// path when known. Every entry of `extensions` is added as an extension member.
+pub(crate) fn problem_body(
+    status: StatusCode,
+    detail: &str,
+    context: Option<&RequestContext>,
+    extensions: Map<String, Value>,
+) -> Value {
+    let mut body = json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or("Unknown Error"),
+        "status": status.as_u16(),
+        "detail": detail,
+    });
+    if let Some(context) = context
+        && !context.path.is_empty()
+    {
+        body["instance"] = json!(context.path);
+    }
+    if let Value::Object(members) = &mut body {
+        members.extend(extensions);
+    }
+    body
+}
@@ -0,0 +1,50 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Environment variable enabling verbose error bodies (`1`, `true`, `yes` or `on`).
+pub const ENV_VERBOSE_ERRORS: &str = "HTTP_VERBOSE_ERRORS";
+
+/// Message sent to clients in place of a redacted `5xx` error.
+pub const REDACTED_MESSAGE: &str = "An internal error occurred";
+
+/// Verbosity selected with [`set_verbose_errors`]: `0` when unset, `1` off, `2` on.
+static VERBOSE_ERRORS: AtomicU8 = AtomicU8::new(0);
+
+/// Selects whether error bodies include internal details for the whole process.
+///
+/// When disabled, `5xx` responses carry [`REDACTED_MESSAGE`] and an `incident_id` instead of the
+/// error message, and no stack trace is included; the full error is logged server-side under the
+/// same incident ID. `4xx` responses always keep their message.
+///
+/// Overrides [`ENV_VERBOSE_ERRORS`].
+pub fn set_verbose_errors(verbose: bool) {
+    VERBOSE_ERRORS.store(if verbose { 2 } else { 1 }, Ordering::Relaxed);
+}
+
+/// Returns whether error bodies include internal details.
+///
+/// Resolved from [`set_verbose_errors`], then [`ENV_VERBOSE_ERRORS`], and defaults to `true` in
+/// debug builds and `false` in release builds.
+pub fn verbose_errors() -> bool {
+    match VERBOSE_ERRORS.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => env_verbose_errors().unwrap_or(cfg!(debug_assertions)),
+    }
+}
+
+/// Value of [`ENV_VERBOSE_ERRORS`], read once.
+fn env_verbose_errors() -> Option<bool> {
+    static ENV: OnceLock<Option<bool>> = OnceLock::new();
+    *ENV.get_or_init(|| {
+        let value = std::env::var(ENV_VERBOSE_ERRORS).ok()?;
+        match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Some(true),
+            "0" | "false" | "no" | "off" => Some(false),
+            _ => {
+                log::warn!("Ignoring invalid {ENV_VERBOSE_ERRORS} value: {value}");
+                None
+            }
+        }
+    })
+}
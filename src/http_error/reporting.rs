@@ -0,0 +1,454 @@
+use crate::http_error::backtrace::Frame;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Log target of [`LogReporter`] output.
+pub const ERROR_REPORT_TARGET: &str = "error_report";
+
+/// Reports forwarded per [`DEFAULT_REPORT_WINDOW`] by the built-in reporters.
+pub const DEFAULT_REPORT_LIMIT: u32 = 30;
+
+/// Window of [`DEFAULT_REPORT_LIMIT`].
+pub const DEFAULT_REPORT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Reports waiting to be written by a [`JsonLinesReporter`]; further reports are dropped.
+const JSON_LINES_QUEUE: usize = 256;
+
+/// Reporters invoked for every rendered `5xx` error.
+static REPORTERS: RwLock<Vec<Arc<dyn ErrorReporter>>> = RwLock::new(Vec::new());
+
+/// Everything known about a `5xx` error when its response is rendered.
+#[derive(Clone, Debug, Serialize)]
+pub struct ErrorReport {
+    /// Incident ID also sent to the client as `incident_id`.
+    pub incident_id: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u128,
+    /// HTTP status of the response.
+    pub status: u16,
+    /// Stable error code, see [`codes`](super::codes).
+    pub code: &'static str,
+    /// Unredacted error message.
+    pub message: String,
+    /// HTTP method of the request, empty outside of a request.
+    pub method: String,
+    /// Request path, empty outside of a request.
+    pub path: String,
+    /// Request ID, if request IDs are enabled.
+    pub request_id: Option<String>,
+    /// Error messages from the outermost to the root cause.
+    pub causes: Vec<String>,
+    /// Backtrace captured where the error was created, if backtraces are enabled.
+    pub stacktrace: Vec<Frame>,
+}
+
+/// Receives a report for every `5xx` [`Error`](super::Error) rendered into a response.
+///
+/// Reporters run synchronously while the response is rendered; implementations doing I/O should
+/// hand the report off to a task or thread, and are best wrapped in [`RateLimited`].
+pub trait ErrorReporter: Send + Sync + 'static {
+    /// Handles one report.
+    fn report(&self, report: &ErrorReport);
+}
+
+/// Registers `reporter` for the whole process.
+pub fn register_error_reporter<R: ErrorReporter>(reporter: R) {
+    REPORTERS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Arc::new(reporter));
+}
+
+/// Removes every registered reporter.
+pub fn clear_error_reporters() {
+    REPORTERS.write().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Sends the report built by `build` to every registered reporter. `build` is only called if at
+/// least one reporter is registered.
+pub(crate) fn dispatch(build: impl FnOnce() -> ErrorReport) {
+    let reporters = REPORTERS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if reporters.is_empty() {
+        return;
+    }
+    let report = build();
+    for reporter in reporters {
+        reporter.report(&report);
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+pub(crate) fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+/// Writes each report as one JSON line to the [`ERROR_REPORT_TARGET`] log target.
+///
+/// At most [`DEFAULT_REPORT_LIMIT`] reports are written per [`DEFAULT_REPORT_WINDOW`], see
+/// [`rate_limit`](Self::rate_limit).
+#[derive(Clone, Debug)]
+pub struct LogReporter {
+    limiter: Arc<Limiter>,
+}
+
+impl Default for LogReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogReporter {
+    /// Reporter with the default rate limit.
+    pub fn new() -> Self {
+        Self {
+            limiter: Arc::new(Limiter::new(DEFAULT_REPORT_LIMIT, DEFAULT_REPORT_WINDOW)),
+        }
+    }
+
+    /// Writes at most `max` reports per `window` instead of the default.
+    pub fn rate_limit(mut self, max: u32, window: Duration) -> Self {
+        self.limiter = Arc::new(Limiter::new(max, window));
+        self
+    }
+}
+
+impl ErrorReporter for LogReporter {
+    fn report(&self, report: &ErrorReport) {
+        if !self.limiter.allow() {
+            return;
+        }
+        match serde_json::to_string(report) {
+            Ok(line) => log::error!(target: ERROR_REPORT_TARGET, "{line}"),
+            Err(e) => log::error!("Failed to serialize error report: {e}"),
+        }
+    }
+}
+
+/// Appends each report as one JSON line to a file.
+///
+/// Lines are written by a background thread so rendering the response never waits on the disk.
+/// At most [`DEFAULT_REPORT_LIMIT`] reports are written per [`DEFAULT_REPORT_WINDOW`], see
+/// [`rate_limit`](Self::rate_limit), and reports are dropped while the writer is behind.
+#[derive(Debug)]
+pub struct JsonLinesReporter {
+    lines: SyncSender<Vec<u8>>,
+    limiter: Limiter,
+}
+
+impl JsonLinesReporter {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (lines, queue) = mpsc::sync_channel::<Vec<u8>>(JSON_LINES_QUEUE);
+        let display = path.display().to_string();
+        std::thread::Builder::new()
+            .name("error-report-writer".into())
+            .spawn(move || {
+                // Ends once the reporter is dropped
+                for line in queue {
+                    if let Err(e) = file.write_all(&line) {
+                        log::error!("Failed to write error report to {display}: {e}");
+                    }
+                }
+            })?;
+        Ok(Self {
+            lines,
+            limiter: Limiter::new(DEFAULT_REPORT_LIMIT, DEFAULT_REPORT_WINDOW),
+        })
+    }
+
+    /// Writes at most `max` reports per `window` instead of the default.
+    pub fn rate_limit(mut self, max: u32, window: Duration) -> Self {
+        self.limiter = Limiter::new(max, window);
+        self
+    }
+}
+
+impl ErrorReporter for JsonLinesReporter {
+    fn report(&self, report: &ErrorReport) {
+        if !self.limiter.allow() {
+            return;
+        }
+        let mut line = match serde_json::to_vec(report) {
+            Ok(line) => line,
+            Err(e) => {
+                log::error!("Failed to serialize error report: {e}");
+                return;
+            }
+        };
+        line.push(b'\n');
+        match self.lines.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => log::warn!(
+                "Dropping error report {}: the writer is behind",
+                report.incident_id
+            ),
+            Err(TrySendError::Disconnected(_)) => log::warn!(
+                "Dropping error report {}: the writer stopped",
+                report.incident_id
+            ),
+        }
+    }
+}
+
+/// POSTs each report as JSON to a webhook.
+///
+/// Requests are sent on a background task so rendering the response never waits on the webhook.
+/// At most [`DEFAULT_REPORT_LIMIT`] reports are sent per [`DEFAULT_REPORT_WINDOW`], see
+/// [`rate_limit`](Self::rate_limit). Reports raised outside of a Tokio runtime are dropped.
+#[derive(Clone, Debug)]
+pub struct WebhookReporter {
+    client: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    limiter: Arc<Limiter>,
+}
+
+impl WebhookReporter {
+    /// Default timeout of webhook requests.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Reporter posting to `url`.
+    pub fn new(url: impl Into<String>) -> reqwest::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Self::DEFAULT_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            url: url.into(),
+            headers: Vec::new(),
+            limiter: Arc::new(Limiter::new(DEFAULT_REPORT_LIMIT, DEFAULT_REPORT_WINDOW)),
+        })
+    }
+
+    /// Adds a header to every request, e.g. for authentication.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends at most `max` reports per `window` instead of the default.
+    pub fn rate_limit(mut self, max: u32, window: Duration) -> Self {
+        self.limiter = Arc::new(Limiter::new(max, window));
+        self
+    }
+}
+
+impl ErrorReporter for WebhookReporter {
+    fn report(&self, report: &ErrorReport) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            log::warn!(
+                "Dropping error report {}: no async runtime",
+                report.incident_id
+            );
+            return;
+        };
+        if !self.limiter.allow() {
+            return;
+        }
+        let mut request = self.client.post(&self.url).json(report);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let incident_id = report.incident_id.clone();
+        runtime.spawn(async move {
+            match request.send().await.and_then(|r| r.error_for_status()) {
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to deliver error report {incident_id}: {e}"),
+            }
+        });
+    }
+}
+
+/// Forwards at most `max` reports per `window` to the wrapped reporter and drops the rest.
+///
+/// The number of dropped reports is logged when the next window starts.
+#[derive(Debug)]
+pub struct RateLimited<R> {
+    inner: R,
+    limiter: Limiter,
+}
+
+impl<R: ErrorReporter> RateLimited<R> {
+    /// Wraps `inner`, allowing `max` reports per `window`.
+    pub fn new(inner: R, max: u32, window: Duration) -> Self {
+        Self {
+            inner,
+            limiter: Limiter::new(max, window),
+        }
+    }
+}
+
+impl<R: ErrorReporter> ErrorReporter for RateLimited<R> {
+    fn report(&self, report: &ErrorReport) {
+        if self.limiter.allow() {
+            self.inner.report(report);
+        }
+    }
+}
+
+/// Fixed window counter behind [`RateLimited`] and the built-in reporters.
+#[derive(Debug)]
+struct Limiter {
+    max: u32,
+    window: Duration,
+    state: Mutex<WindowState>,
+}
+
+#[derive(Debug)]
+struct WindowState {
+    started: Instant,
+    sent: u32,
+    dropped: u32,
+}
+
+impl Limiter {
+    fn new(max: u32, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            state: Mutex::new(WindowState {
+                started: Instant::now(),
+                sent: 0,
+                dropped: 0,
+            }),
+        }
+    }
+
+    /// Counts one report, returning `false` if it is over the limit and must be dropped.
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.started.elapsed() >= self.window {
+            if state.dropped > 0 {
+                log::warn!("Dropped {} error report(s) over rate limit", state.dropped);
+            }
+            *state = WindowState {
+                started: Instant::now(),
+                sent: 0,
+                dropped: 0,
+            };
+        }
+        if state.sent >= self.max {
+            state.dropped += 1;
+            return false;
+        }
+        state.sent += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpResponse, HttpServer, web};
+
+    fn report(incident_id: &str) -> ErrorReport {
+        ErrorReport {
+            incident_id: incident_id.to_string(),
+            timestamp: now_millis(),
+            status: 500,
+            code: "INTERNAL_ERROR",
+            message: "boom".to_string(),
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            request_id: None,
+            causes: Vec::new(),
+            stacktrace: Vec::new(),
+        }
+    }
+
+    /// Waits up to two seconds for `done`.
+    async fn eventually(done: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[test]
+    fn log_reports_are_rate_limited() {
+        let reporter = LogReporter::new().rate_limit(1, Duration::from_secs(60));
+        reporter.report(&report("incident-0"));
+        reporter.report(&report("incident-1"));
+
+        let state = reporter.limiter.state.lock().unwrap();
+        assert_eq!((state.sent, state.dropped), (1, 1));
+    }
+
+    #[actix_web::test]
+    async fn webhook_posts_are_rate_limited() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        let server = HttpServer::new(move || {
+            let seen = seen.clone();
+            App::new().route(
+                "/hook",
+                web::post().to(move |body: web::Json<serde_json::Value>| {
+                    seen.lock().unwrap().push(body.into_inner());
+                    async { HttpResponse::NoContent().finish() }
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let port = server.addrs()[0].port();
+        actix_web::rt::spawn(server.run());
+
+        let reporter = WebhookReporter::new(format!("http://127.0.0.1:{port}/hook"))
+            .unwrap()
+            .rate_limit(2, Duration::from_secs(60));
+        for i in 0..5 {
+            reporter.report(&report(&format!("incident-{i}")));
+        }
+
+        eventually(|| received.lock().unwrap().len() >= 2).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut ids = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|r| r["incident_id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ["incident-0", "incident-1"]);
+    }
+
+    #[actix_web::test]
+    async fn json_lines_are_written_in_the_background() {
+        let path = std::env::temp_dir().join(format!("errors-{}.jsonl", uuid::Uuid::new_v4()));
+        let reporter = JsonLinesReporter::open(&path)
+            .unwrap()
+            .rate_limit(2, Duration::from_secs(60));
+        for i in 0..3 {
+            reporter.report(&report(&format!("incident-{i}")));
+        }
+
+        let lines = || {
+            std::fs::read_to_string(&path)
+                .unwrap_or_default()
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .collect::<Vec<_>>()
+        };
+        eventually(|| lines().len() >= 2).await;
+        drop(reporter);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let written = lines();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(written[0]["incident_id"], "incident-0");
+        assert_eq!(written[1]["status"], 500);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::http_error::codes::{self, ErrorCode};
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpRequest, web};
+use futures_util::future::LocalBoxFuture;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// One invalid field of a request.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldError {
+    /// Dotted path of the field, e.g. `address.zip` or `items.2.quantity`.
+    pub path: String,
+    /// Stable code of the failed rule, e.g. `REQUIRED` or `TOO_LONG`.
+    pub code: &'static str,
+    /// Human-readable description.
+    pub message: String,
+    /// Values describing the rule, e.g. `{ "max": 64 }`.
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub params: Map<String, Value>,
+}
+
+impl FieldError {
+    /// Adds a parameter describing the failed rule.
+    pub fn param(&mut self, name: &str, value: impl Serialize) -> &mut Self {
+        self.params.insert(name.to_string(), json!(value));
+        self
+    }
+}
+
+/// Field-level validation failures, rendered as `422 Unprocessable Entity` with code
+/// `VALIDATION` and a `fields` array:
+///
+/// ```json
+/// {
+///   "code": "VALIDATION",
+///   "status": 422,
+///   "message": "2 invalid field(s): name: must not be empty; age: must be at least 18",
+///   "fields": [
+///     { "path": "name", "code": "REQUIRED", "message": "must not be empty" },
+///     { "path": "age", "code": "TOO_SMALL", "message": "must be at least 18", "params": { "min": 18 } }
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ValidationErrors {
+    fields: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an invalid field. Returns the error so parameters can be added to it.
+    pub fn add(
+        &mut self,
+        path: impl Into<String>,
+        code: &'static str,
+        message: impl Into<String>,
+    ) -> &mut FieldError {
+        self.fields.push(FieldError {
+            path: path.into(),
+            code,
+            message: message.into(),
+            params: Map::new(),
+        });
+        self.fields.last_mut().expect("field error was just pushed")
+    }
+
+    /// Records an already built field error.
+    pub fn push(&mut self, error: FieldError) {
+        self.fields.push(error);
+    }
+
+    /// Adds the errors of a nested value, prefixing their paths with `prefix`.
+    pub fn merge(&mut self, prefix: &str, nested: ValidationErrors) {
+        for mut error in nested.fields {
+            error.path = if error.path.is_empty() {
+                prefix.to_string()
+            } else {
+                format!("{prefix}.{}", error.path)
+            };
+            self.fields.push(error);
+        }
+    }
+
+    /// Returns the recorded field errors.
+    pub fn fields(&self) -> &[FieldError] {
+        &self.fields
+    }
+
+    /// Returns `true` if no field error was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// `Ok(())` if no field error was recorded, `Err(self)` otherwise.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+
+    /// Extension members added to the error body.
+    pub(crate) fn details(&self) -> Map<String, Value> {
+        let mut details = Map::new();
+        details.insert("fields".to_string(), json!(self.fields));
+        details
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid field(s)", self.fields.len())?;
+        for (i, error) in self.fields.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{separator}{}: {}", error.path, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl ErrorCode for ValidationErrors {
+    fn code(&self) -> &'static str {
+        codes::VALIDATION
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNPROCESSABLE_ENTITY
+    }
+
+    fn params(&self) -> Map<String, Value> {
+        let mut params = Map::new();
+        params.insert("count".to_string(), json!(self.fields.len()));
+        params
+    }
+}
+
+/// Types that can check their own contents.
+///
+/// Implemented for `web::Json`, `web::Query`, `web::Form` and `web::Path` of a `Validate` type so
+/// they can be wrapped in [`Valid`].
+pub trait Validate {
+    /// Returns every invalid field.
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+macro_rules! impl_validate_for_extractor {
+    ($($extractor:ident),*) => {$(
+        impl<T: Validate> Validate for web::$extractor<T> {
+            fn validate(&self) -> Result<(), ValidationErrors> {
+                (**self).validate()
+            }
+        }
+    )*};
+}
+
+impl_validate_for_extractor!(Json, Query, Form, Path);
+
+/// Extractor running [`Validate::validate`] on the output of another extractor before the handler
+/// executes, rejecting the request with [`ValidationErrors`] when it fails.
+///
+/// ```norust
+/// #[post("/users")]
+/// async fn create_user(user: Valid<web::Json<NewUser>>) -> http_error::Result<HttpResponse> {
+///     let user = user.into_inner().into_inner();
+///     ...
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Valid<T>(pub T);
+
+impl<T> Valid<T> {
+    /// Unwraps the validated extractor.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Valid<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Valid<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> FromRequest for Valid<T>
+where
+    T: FromRequest + Validate + 'static,
+    T::Future: 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let extract = T::from_request(req, payload);
+        Box::pin(async move {
+            let value = extract.await.map_err(Into::into)?;
+            value.validate().map_err(super::Error::coded)?;
+            Ok(Valid(value))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use actix_web::{App, HttpResponse};
+    use serde::Deserialize;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[derive(Deserialize)]
+    struct NewUser {
+        name: String,
+        age: u32,
+    }
+
+    impl Validate for NewUser {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            if self.name.is_empty() {
+                errors.add("name", "REQUIRED", "must not be empty");
+            }
+            if self.age < 18 {
+                errors
+                    .add("age", "TOO_SMALL", "must be at least 18")
+                    .param("min", 18);
+            }
+            errors.into_result()
+        }
+    }
+
+    async fn create_user(user: &NewUser) -> (StatusCode, Value, bool) {
+        let called = Arc::new(AtomicBool::new(false));
+        let handler_called = called.clone();
+        let app = init_service(App::new().route(
+            "/users",
+            web::post().to(move |_: Valid<web::Json<NewUser>>| {
+                handler_called.store(true, Ordering::SeqCst);
+                async { HttpResponse::Created().finish() }
+            }),
+        ))
+        .await;
+        let request = TestRequest::post()
+            .uri("/users")
+            .set_json(json!({ "name": user.name, "age": user.age }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        let status = response.status();
+        let body = if status.is_success() {
+            Value::Null
+        } else {
+            read_body_json(response).await
+        };
+        (status, body, called.load(Ordering::SeqCst))
+    }
+
+    #[actix_web::test]
+    async fn invalid_input_is_rejected_before_the_handler() {
+        let user = NewUser {
+            name: String::new(),
+            age: 16,
+        };
+        let (status, body, called) = create_user(&user).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(!called);
+        assert_eq!(body["code"], "VALIDATION");
+        assert_eq!(body["status"], 422);
+        assert_eq!(
+            body["fields"],
+            json!([
+                { "path": "name", "code": "REQUIRED", "message": "must not be empty" },
+                {
+                    "path": "age",
+                    "code": "TOO_SMALL",
+                    "message": "must be at least 18",
+                    "params": { "min": 18 }
+                }
+            ])
+        );
+    }
+
+    #[actix_web::test]
+    async fn valid_input_reaches_the_handler() {
+        let user = NewUser {
+            name: "Ada".to_string(),
+            age: 36,
+        };
+        let (status, _, called) = create_user(&user).await;
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(called);
+    }
+}